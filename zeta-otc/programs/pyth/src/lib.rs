@@ -14,7 +14,10 @@ pub mod pyth {
         let mut price_oracle = pc::Price::load(&oracle).unwrap();
 
         price_oracle.agg.price = price;
+        price_oracle.twap = price;
         price_oracle.agg.conf = conf;
+        price_oracle.agg.status = pc::PriceStatus::Trading;
+        price_oracle.agg.pub_slot = Clock::get()?.slot;
         price_oracle.expo = expo;
         price_oracle.ptype = pc::PriceType::Price;
         Ok(())
@@ -24,6 +27,43 @@ pub mod pyth {
         let oracle = &ctx.accounts.price;
         let mut price_oracle = pc::Price::load(&oracle).unwrap();
         price_oracle.agg.price = price as i64;
+        price_oracle.agg.pub_slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    pub fn set_confidence(ctx: Context<SetPrice>, conf: u64) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = pc::Price::load(&oracle).unwrap();
+        price_oracle.agg.conf = conf;
+        price_oracle.agg.pub_slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    pub fn set_twap(ctx: Context<SetPrice>, twap: i64) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = pc::Price::load(&oracle).unwrap();
+        price_oracle.twap = twap;
+        price_oracle.agg.pub_slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    // Backdates the last publish to simulate a feed that stopped updating.
+    pub fn set_pub_slot(ctx: Context<SetPrice>, pub_slot: u64) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = pc::Price::load(&oracle).unwrap();
+        price_oracle.agg.pub_slot = pub_slot;
+        Ok(())
+    }
+
+    pub fn set_status(ctx: Context<SetPrice>, status: u8) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = pc::Price::load(&oracle).unwrap();
+        price_oracle.agg.status = match status {
+            1 => pc::PriceStatus::Trading,
+            2 => pc::PriceStatus::Halted,
+            3 => pc::PriceStatus::Auction,
+            _ => pc::PriceStatus::Unknown,
+        };
         Ok(())
     }
 }
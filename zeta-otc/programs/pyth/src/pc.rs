@@ -1,7 +1,7 @@
 // Taken from https://github.com/project-serum/anchor/tree/master/examples/pyth
 use crate::*;
 use anchor_lang::prelude::AccountInfo;
-use bytemuck::{cast_slice_mut, from_bytes_mut, try_cast_slice_mut, Pod, Zeroable};
+use bytemuck::{from_bytes_mut, try_from_bytes_mut, Pod, Zeroable};
 use std::cell::RefMut;
 
 #[derive(Default, Copy, Clone)]
@@ -100,14 +100,17 @@ pub struct Price {
 impl Price {
     #[inline]
     pub fn load<'a>(price_feed: &'a AccountInfo) -> Result<RefMut<'a, Price>> {
-        let account_data: RefMut<'a, [u8]>;
+        let mut account_data: RefMut<'a, [u8]>;
         let state: RefMut<'a, Self>;
 
-        account_data = RefMut::map(price_feed.try_borrow_mut_data().unwrap(), |data| *data);
+        account_data = RefMut::map(price_feed.try_borrow_mut_data()?, |data| *data);
 
-        state = RefMut::map(account_data, |data| {
-            from_bytes_mut(cast_slice_mut::<u8, u8>(try_cast_slice_mut(data).unwrap()))
-        });
+        // Wrong sized or misaligned data isn't a price account.
+        if try_from_bytes_mut::<Price>(&mut account_data).is_err() {
+            return Err(ProgramError::InvalidAccountData.into());
+        }
+
+        state = RefMut::map(account_data, |data| from_bytes_mut(data));
         Ok(state)
     }
 }
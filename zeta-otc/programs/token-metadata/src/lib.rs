@@ -0,0 +1,223 @@
+// Stand-in for the Metaplex token metadata program, loaded at the real
+// program id by the zeta-otc tests. It implements create_metadata_accounts_v3
+// with the real accounts, instruction encoding and metadata layout.
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::system_instruction;
+use anchor_spl::token::Mint;
+
+declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+pub const METADATA_SEED: &str = "metadata";
+pub const MAX_NAME_LENGTH: usize = 32;
+pub const MAX_SYMBOL_LENGTH: usize = 10;
+pub const MAX_URI_LENGTH: usize = 200;
+pub const MAX_CREATOR_LIMIT: usize = 5;
+pub const MAX_METADATA_LEN: usize = 679;
+pub const METADATA_V1_KEY: u8 = 4;
+pub const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+
+#[cfg(not(feature = "no-entrypoint"))]
+anchor_lang::solana_program::entrypoint!(process_instruction);
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let (tag, rest) = data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    match *tag {
+        CREATE_METADATA_ACCOUNT_V3 => {
+            let args = CreateMetadataAccountArgsV3::try_from_slice(rest)
+                .map_err(|_| ProgramError::InvalidInstructionData)?;
+            create_metadata_accounts_v3(program_id, accounts, args)
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn create_metadata_accounts_v3(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    args: CreateMetadataAccountArgsV3,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let metadata_info = next_account_info(account_info_iter)?;
+    let mint_info = next_account_info(account_info_iter)?;
+    let mint_authority_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let update_authority_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let data = args.data;
+    if data.name.len() > MAX_NAME_LENGTH
+        || data.symbol.len() > MAX_SYMBOL_LENGTH
+        || data.uri.len() > MAX_URI_LENGTH
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if let Some(creators) = &data.creators {
+        if creators.len() > MAX_CREATOR_LIMIT {
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+
+    let (metadata_address, metadata_nonce) = Pubkey::find_program_address(
+        &[
+            METADATA_SEED.as_bytes(),
+            program_id.as_ref(),
+            mint_info.key.as_ref(),
+        ],
+        program_id,
+    );
+    if metadata_address != *metadata_info.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !metadata_info.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if *mint_info.owner != anchor_spl::token::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mint = Mint::try_deserialize(&mut &mint_info.data.borrow()[..])?;
+    if mint.mint_authority != COption::Some(*mint_authority_info.key) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !mint_authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    invoke_signed(
+        &system_instruction::create_account(
+            payer_info.key,
+            metadata_info.key,
+            Rent::get()?.minimum_balance(MAX_METADATA_LEN),
+            MAX_METADATA_LEN as u64,
+            program_id,
+        ),
+        &[
+            payer_info.clone(),
+            metadata_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&[
+            METADATA_SEED.as_bytes(),
+            program_id.as_ref(),
+            mint_info.key.as_ref(),
+            &[metadata_nonce],
+        ]],
+    )?;
+
+    let metadata = Metadata {
+        key: METADATA_V1_KEY,
+        update_authority: *update_authority_info.key,
+        mint: *mint_info.key,
+        data: Data {
+            name: puff(&data.name, MAX_NAME_LENGTH),
+            symbol: puff(&data.symbol, MAX_SYMBOL_LENGTH),
+            uri: puff(&data.uri, MAX_URI_LENGTH),
+            seller_fee_basis_points: data.seller_fee_basis_points,
+            creators: data.creators,
+        },
+        primary_sale_happened: false,
+        is_mutable: args.is_mutable,
+        edition_nonce: None,
+        token_standard: None,
+        collection: data.collection,
+        uses: data.uses,
+        collection_details: args.collection_details,
+    };
+    metadata
+        .serialize(&mut &mut metadata_info.data.borrow_mut()[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)?;
+    Ok(())
+}
+
+// Strings are stored null padded to their maximum length.
+fn puff(value: &str, length: usize) -> String {
+    let mut puffed = value.to_string();
+    puffed.push_str(&"\0".repeat(length - value.len()));
+    puffed
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Collection {
+    pub verified: bool,
+    pub key: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum UseMethod {
+    Burn,
+    Multiple,
+    Single,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Uses {
+    pub use_method: UseMethod,
+    pub remaining: u64,
+    pub total: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum CollectionDetails {
+    V1 { size: u64 },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DataV2 {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+    pub collection: Option<Collection>,
+    pub uses: Option<Uses>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreateMetadataAccountArgsV3 {
+    pub data: DataV2,
+    pub is_mutable: bool,
+    pub collection_details: Option<CollectionDetails>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Data {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+}
+
+// Layout of the metadata account, without the trailing fields zeta-otc
+// never sets.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Metadata {
+    pub key: u8,
+    pub update_authority: Pubkey,
+    pub mint: Pubkey,
+    pub data: Data,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    pub token_standard: Option<u8>,
+    pub collection: Option<Collection>,
+    pub uses: Option<Uses>,
+    pub collection_details: Option<CollectionDetails>,
+}
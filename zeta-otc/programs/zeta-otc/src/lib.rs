@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::program_option::COption;
 use anchor_spl::token::{self, Burn, CloseAccount, Mint, MintTo, Token, TokenAccount, Transfer};
 use pyth::pc;
+use token_metadata::TokenMetadata;
+
+pub mod token_metadata;
 
 declare_id!("4HA5LaXGeijJA3QTtdEKCXJwyf6PTSGB9CfoZAvmtkT1");
 
@@ -11,8 +16,19 @@ pub const VAULT_AUTH_SEED: &str = "vault-auth";
 pub const VAULT_SEED: &str = "vault";
 pub const OPTION_ACCOUNT_SEED: &str = "option-account";
 pub const OPTION_MINT_SEED: &str = "option-mint";
+pub const SETTLEMENT_VAULT_SEED: &str = "settlement-vault";
+pub const WRITER_MINT_SEED: &str = "writer-mint";
+pub const SETTLEMENT_OBSERVATIONS_SEED: &str = "settlement-observations";
+pub const QUEUED_ACTION_SEED: &str = "queued-action";
+pub const TREASURY_SEED: &str = "treasury";
+pub const MAX_SETTLEMENT_OBSERVATIONS: usize = 32;
+pub const MAX_ORACLES: usize = 5;
 pub const OPTION_MINT_DECIMALS: u8 = 4;
 pub const USDC_DECIMALS: u32 = 6;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const SECONDS_PER_DAY: u64 = 86_400;
+pub const MAX_NEXT_EXPIRIES: usize = 16;
+pub const MAX_SYMBOL_LENGTH: usize = 10;
 
 #[program]
 pub mod zeta_otc {
@@ -22,12 +38,177 @@ pub mod zeta_otc {
         ctx: Context<InitializeState>,
         args: InitializeStateArgs,
     ) -> ProgramResult {
+        // Unclaimed funds are swept once the claim period ends, so it has to
+        // give holders and writers a real chance to claim.
+        if args.claim_period_seconds == 0
+            || args.claim_period_seconds < args.settlement_price_threshold_seconds
+        {
+            return Err(ErrorCode::InvalidClaimPeriod.into());
+        }
+        // Physical holders have to be able to exercise before close_option
+        // can sweep the reserve.
+        if args.exercise_period_seconds == 0
+            || args.exercise_period_seconds > args.claim_period_seconds
+        {
+            return Err(ErrorCode::InvalidExercisePeriod.into());
+        }
+
         ctx.accounts.state.state_nonce = args.state_nonce;
         ctx.accounts.state.mint_auth_nonce = args.mint_auth_nonce;
         ctx.accounts.state.vault_auth_nonce = args.vault_auth_nonce;
         ctx.accounts.state.admin = ctx.accounts.admin.key();
+        ctx.accounts.state.quote_mint = ctx.accounts.quote_mint.key();
         ctx.accounts.state.settlement_price_threshold_seconds =
             args.settlement_price_threshold_seconds;
+        ctx.accounts.state.settlement_dispute_seconds = args.settlement_dispute_seconds;
+        ctx.accounts.state.guardian = ctx.accounts.guardian.key();
+        ctx.accounts.state.listing_authority = ctx.accounts.admin.key();
+        ctx.accounts.state.settlement_authority = ctx.accounts.admin.key();
+        ctx.accounts.state.admin_action_delay_seconds = args.admin_action_delay_seconds;
+        ctx.accounts.state.claim_period_seconds = args.claim_period_seconds;
+        ctx.accounts.state.exercise_period_seconds = args.exercise_period_seconds;
+        emit!(StateInitialized {
+            state: ctx.accounts.state.key(),
+            admin: ctx.accounts.admin.key(),
+            quote_mint: ctx.accounts.quote_mint.key(),
+        });
+        Ok(())
+    }
+
+    // Admin rotation is two step so a typo can't lock the protocol.
+    pub fn propose_admin(ctx: Context<ProposeAdmin>) -> ProgramResult {
+        ctx.accounts.state.pending_admin = ctx.accounts.new_admin.key();
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> ProgramResult {
+        ctx.accounts.state.admin = ctx.accounts.new_admin.key();
+        ctx.accounts.state.pending_admin = Pubkey::default();
+        Ok(())
+    }
+
+    pub fn update_roles(ctx: Context<UpdateRoles>) -> ProgramResult {
+        ctx.accounts.state.listing_authority = ctx.accounts.listing_authority.key();
+        ctx.accounts.state.settlement_authority = ctx.accounts.settlement_authority.key();
+        ctx.accounts.state.guardian = ctx.accounts.guardian.key();
+        Ok(())
+    }
+
+    // Risk sensitive changes are queued so holders have
+    // `admin_action_delay_seconds` to react before they apply.
+    pub fn queue_admin_action(
+        ctx: Context<QueueAdminAction>,
+        queued_action_nonce: u8,
+        action: AdminAction,
+    ) -> ProgramResult {
+        let clock = Clock::get()?;
+        let state = &mut ctx.accounts.state;
+        let queued_action = &mut ctx.accounts.queued_action;
+        queued_action.queued_action_nonce = queued_action_nonce;
+        queued_action.index = state.queued_action_count;
+        queued_action.action = action;
+        queued_action.eta = (clock.unix_timestamp as u64)
+            .checked_add(state.admin_action_delay_seconds.into())
+            .unwrap();
+        state.queued_action_count = state.queued_action_count.checked_add(1).unwrap();
+        Ok(())
+    }
+
+    pub fn cancel_admin_action(_ctx: Context<CancelAdminAction>) -> ProgramResult {
+        Ok(())
+    }
+
+    // Oracle changes take the underlying as the first remaining account.
+    pub fn execute_admin_action(ctx: Context<ExecuteAdminAction>) -> ProgramResult {
+        let clock = Clock::get()?;
+        if clock.unix_timestamp < ctx.accounts.queued_action.eta as i64 {
+            msg!(
+                "Current time {} < Queued action eta {}",
+                clock.unix_timestamp,
+                ctx.accounts.queued_action.eta
+            );
+            return Err(ErrorCode::AdminActionNotReady.into());
+        }
+
+        let action = ctx.accounts.queued_action.action;
+        match action {
+            AdminAction::SetSettlementPriceThresholdSeconds { seconds } => {
+                ctx.accounts.state.settlement_price_threshold_seconds = seconds;
+                return Ok(());
+            }
+            AdminAction::SetSettlementDisputeSeconds { seconds } => {
+                ctx.accounts.state.settlement_dispute_seconds = seconds;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let underlying_info = match ctx.remaining_accounts.first() {
+            Some(info) if Some(info.key()) == action.get_underlying() => info,
+            _ => return Err(ErrorCode::InvalidAdminActionAccounts.into()),
+        };
+        let mut underlying_account = Account::<Underlying>::try_from(underlying_info)?;
+        apply_oracle_action(&mut underlying_account, action)?;
+        underlying_account.exit(ctx.program_id)?;
+
+        Ok(())
+    }
+
+    // The guardian can halt each part of the protocol during an incident,
+    // either globally or for a single underlying.
+    pub fn set_pause_flags(ctx: Context<SetPauseFlags>, pause_flags: PauseFlags) -> ProgramResult {
+        ctx.accounts.state.pause_flags = pause_flags;
+        Ok(())
+    }
+
+    pub fn set_underlying_pause_flags(
+        ctx: Context<SetUnderlyingPauseFlags>,
+        pause_flags: PauseFlags,
+    ) -> ProgramResult {
+        ctx.accounts.underlying.pause_flags = pause_flags;
+        Ok(())
+    }
+
+    pub fn initialize_treasury(
+        ctx: Context<InitializeTreasury>,
+        _treasury_nonce: u8,
+    ) -> ProgramResult {
+        msg!("Initialized treasury for mint {}", ctx.accounts.mint.key());
+        Ok(())
+    }
+
+    pub fn set_fees(
+        ctx: Context<SetFees>,
+        mint_fee_bps: u64,
+        exercise_fee_bps: u64,
+        settlement_fee_bps: u64,
+    ) -> ProgramResult {
+        if mint_fee_bps > BPS_DENOMINATOR
+            || exercise_fee_bps > BPS_DENOMINATOR
+            || settlement_fee_bps > BPS_DENOMINATOR
+        {
+            return Err(ErrorCode::InvalidFeeRate.into());
+        }
+        ctx.accounts.state.mint_fee_bps = mint_fee_bps;
+        ctx.accounts.state.exercise_fee_bps = exercise_fee_bps;
+        ctx.accounts.state.settlement_fee_bps = settlement_fee_bps;
+        Ok(())
+    }
+
+    pub fn withdraw_from_treasury(
+        ctx: Context<WithdrawFromTreasury>,
+        amount: u64,
+    ) -> ProgramResult {
+        let state_seeds = state_authority! {
+            bump = ctx.accounts.state.state_nonce
+        };
+
+        token::transfer(
+            ctx.accounts
+                .into_transfer_context()
+                .with_signer(&[&state_seeds[..]]),
+            amount,
+        )?;
         Ok(())
     }
 
@@ -35,35 +216,150 @@ pub mod zeta_otc {
         ctx: Context<InitializeUnderlying>,
         args: InitializeUnderlyingArgs,
     ) -> ProgramResult {
-        if ctx.accounts.state.admin != ctx.accounts.admin.key() {
-            return Err(ErrorCode::UnauthorizedAdmin.into());
+        if ctx.accounts.state.listing_authority != ctx.accounts.listing_authority.key() {
+            return Err(ErrorCode::UnauthorizedListingAuthority.into());
+        }
+        if args.symbol.is_empty()
+            || args.symbol.len() > MAX_SYMBOL_LENGTH
+            || !args.symbol.bytes().all(|b| b.is_ascii_alphanumeric())
+        {
+            return Err(ErrorCode::InvalidUnderlyingSymbol.into());
         }
         ctx.accounts.underlying.underlying_nonce = args.underlying_nonce;
+        ctx.accounts.underlying.symbol[..args.symbol.len()].copy_from_slice(args.symbol.as_bytes());
         ctx.accounts.underlying.mint = ctx.accounts.mint.key();
         ctx.accounts.underlying.oracle = ctx.accounts.oracle.key();
+        ctx.accounts.underlying.max_confidence_bps = args.max_confidence_bps;
+        ctx.accounts.underlying.max_staleness_slots = args.max_staleness_slots;
+        ctx.accounts.underlying.oracles[0] = ctx.accounts.oracle.key();
+        ctx.accounts.underlying.oracle_types[0] = OracleType::Pyth;
+        ctx.accounts.underlying.oracle_count = 1;
+        ctx.accounts.underlying.oracle_quorum = 1;
+        emit!(UnderlyingInitialized {
+            underlying: ctx.accounts.underlying.key(),
+            mint: ctx.accounts.mint.key(),
+            oracle: ctx.accounts.oracle.key(),
+        });
+        Ok(())
+    }
+
+    pub fn set_option_limits(
+        ctx: Context<SetOptionLimits>,
+        args: SetOptionLimitsArgs,
+    ) -> ProgramResult {
+        if args.max_moneyness_bps != 0 && args.max_moneyness_bps < args.min_moneyness_bps {
+            return Err(ErrorCode::InvalidOptionLimits.into());
+        }
+        if args.max_tenor_seconds != 0 && args.max_tenor_seconds < args.min_time_to_expiry_seconds {
+            return Err(ErrorCode::InvalidOptionLimits.into());
+        }
+
+        let underlying = &mut ctx.accounts.underlying;
+        underlying.min_moneyness_bps = args.min_moneyness_bps;
+        underlying.max_moneyness_bps = args.max_moneyness_bps;
+        underlying.strike_tick_size = args.strike_tick_size;
+        underlying.min_time_to_expiry_seconds = args.min_time_to_expiry_seconds;
+        underlying.max_tenor_seconds = args.max_tenor_seconds;
+        Ok(())
+    }
+
+    pub fn set_expiry_calendar(
+        ctx: Context<SetExpiryCalendar>,
+        expiry_calendar: ExpiryCalendar,
+    ) -> ProgramResult {
+        if expiry_calendar.time_of_day_seconds as u64 >= SECONDS_PER_DAY {
+            return Err(ErrorCode::InvalidExpiryCalendar.into());
+        }
+        ctx.accounts.underlying.expiry_calendar = expiry_calendar;
+        Ok(())
+    }
+
+    // Read only, clients simulate it and take the upcoming expiries from the
+    // ValidExpiries event.
+    pub fn get_next_valid_expiries(ctx: Context<GetNextValidExpiries>, count: u8) -> ProgramResult {
+        if count as usize > MAX_NEXT_EXPIRIES {
+            return Err(ErrorCode::TooManyExpiriesRequested.into());
+        }
+
+        let clock = Clock::get()?;
+        emit!(ValidExpiries {
+            underlying: ctx.accounts.underlying.key(),
+            expiries: ctx
+                .accounts
+                .underlying
+                .expiry_calendar
+                .next_valid_expiries(clock.unix_timestamp as u64, count as usize),
+        });
+        Ok(())
+    }
+
+    pub fn initialize_admin_price(ctx: Context<InitializeAdminPrice>) -> ProgramResult {
+        ctx.accounts.admin_price.authority = ctx.accounts.authority.key();
+        Ok(())
+    }
+
+    pub fn set_admin_price(ctx: Context<SetAdminPrice>, price: u64, conf: u64) -> ProgramResult {
+        let admin_price = &mut ctx.accounts.admin_price;
+        admin_price.price = price;
+        admin_price.conf = conf;
+        admin_price.pub_slot = Clock::get()?.slot;
         Ok(())
     }
 
-    // TODO sense check the strike.
     pub fn initialize_option(
         ctx: Context<InitializeOption>,
         args: InitializeOptionArgs,
     ) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).mint {
+            return Err(ErrorCode::MintingPaused.into());
+        }
+
         let clock = Clock::get()?;
         if clock.unix_timestamp > args.expiry as i64 {
             return Err(ErrorCode::OptionExpirationMustBeInTheFuture.into());
         }
 
+        check_option_limits(
+            &ctx.accounts.underlying,
+            &ctx.accounts.oracle,
+            &args,
+            clock.unix_timestamp,
+        )?;
+
+        if args.kind.is_spread() {
+            let valid_strikes = match args.kind {
+                OptionKind::CallSpread => args.short_strike > args.strike,
+                _ => args.short_strike < args.strike,
+            };
+            if !valid_strikes {
+                return Err(ErrorCode::InvalidSpreadStrikes.into());
+            }
+            if args.settlement_mode != SettlementMode::Cash {
+                return Err(ErrorCode::SpreadsMustBeCashSettled.into());
+            }
+        }
+
         let option_account = &mut ctx.accounts.option_account;
         option_account.option_account_nonce = args.option_account_nonce;
         option_account.option_mint_nonce = args.option_mint_nonce;
         option_account.creator_option_token_account_nonce = args.token_account_nonce;
         option_account.vault_nonce = args.vault_nonce;
+        option_account.settlement_vault_nonce = args.settlement_vault_nonce;
+        option_account.writer_mint_nonce = args.writer_mint_nonce;
+        option_account.creator_writer_token_account_nonce = args.writer_token_account_nonce;
 
         option_account.option_mint = ctx.accounts.option_mint.key();
+        option_account.writer_mint = ctx.accounts.writer_mint.key();
         option_account.underlying_mint = ctx.accounts.underlying_mint.key();
+        option_account.collateral_mint = ctx.accounts.collateral_mint.key();
+        option_account.settlement_mint = ctx.accounts.settlement_mint.key();
         option_account.creator = ctx.accounts.creator.key();
+        option_account.kind = args.kind;
+        option_account.settlement_mode = args.settlement_mode;
+        option_account.exercise_style = args.exercise_style;
+        option_account.settlement_source = args.settlement_source;
         option_account.strike = args.strike;
+        option_account.short_strike = args.short_strike;
         option_account.expiry = args.expiry;
 
         option_account.underlying_count = ctx.accounts.underlying.count;
@@ -74,7 +370,10 @@ pub mod zeta_otc {
         };
 
         let collateral_min_lot_size: u64 =
-            get_token_amount_per_option(&ctx.accounts.underlying_mint);
+            get_collateral_amount_per_option(option_account, &ctx.accounts.collateral_mint);
+        if collateral_min_lot_size == 0 {
+            return Err(ErrorCode::InvalidStrike.into());
+        }
         assert!(args.collateral_amount % collateral_min_lot_size == 0);
 
         let mint_amount = args
@@ -89,21 +388,148 @@ pub mod zeta_otc {
             mint_amount,
         )?;
 
+        // One writer token per option, redeemable for the writer's share of
+        // the collateral left after settlement.
+        token::mint_to(
+            ctx.accounts
+                .into_mint_writer_to_context()
+                .with_signer(&[&mint_seeds[..]]),
+            mint_amount,
+        )?;
+
         token::transfer(ctx.accounts.into_transfer_context(), args.collateral_amount)?;
 
+        // The mint fee is paid on top of the collateral.
+        let mint_fee = get_fee(args.collateral_amount, ctx.accounts.state.mint_fee_bps);
+        if mint_fee > 0 {
+            token::transfer(ctx.accounts.into_fee_transfer_context(), mint_fee)?;
+        }
+
+        // Names the option mint so wallets don't show it as an unknown token.
+        let name = get_option_name(ctx.accounts.underlying.get_symbol(), &args);
+        if name.len() > token_metadata::MAX_NAME_LENGTH {
+            return Err(ErrorCode::OptionNameTooLong.into());
+        }
+        let symbol = ctx.accounts.underlying.get_symbol().to_string();
+        ctx.accounts
+            .create_option_metadata(name, symbol, &[&mint_seeds[..]])?;
+
+        emit!(OptionInitialized {
+            option_account: ctx.accounts.option_account.key(),
+            underlying: ctx.accounts.underlying.key(),
+            creator: ctx.accounts.creator.key(),
+            kind: args.kind,
+            settlement_mode: args.settlement_mode,
+            exercise_style: args.exercise_style,
+            settlement_source: args.settlement_source,
+            strike: args.strike,
+            short_strike: args.short_strike,
+            expiry: args.expiry,
+            collateral_amount: args.collateral_amount,
+            option_amount: mint_amount,
+            mint_fee,
+        });
+
+        Ok(())
+    }
+
+    // Lets any writer add collateral to an existing series and mint more of
+    // the same option token.
+    pub fn write_more_options(
+        ctx: Context<WriteMoreOptions>,
+        collateral_amount: u64,
+    ) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).mint {
+            return Err(ErrorCode::MintingPaused.into());
+        }
+
+        let clock = Clock::get()?;
+        if clock.unix_timestamp > ctx.accounts.option_account.expiry as i64 {
+            return Err(ErrorCode::CannotWriteOptionsAfterExpiry.into());
+        }
+
+        match ctx.accounts.option_account.status {
+            OptionStatus::Active => {}
+            OptionStatus::Settling => return Err(ErrorCode::OptionIsSettling.into()),
+            OptionStatus::Settled | OptionStatus::Disputed => {
+                return Err(ErrorCode::CannotWriteOptionsAfterSettlementPriceIsSet.into());
+            }
+            OptionStatus::Closed => return Err(ErrorCode::OptionIsClosed.into()),
+        }
+
+        // Early exercises leave each writer token backed by less than a lot.
+        // New lots would be pooled with them and cover part of the loss.
+        if ctx.accounts.option_mint.supply != ctx.accounts.writer_mint.supply {
+            return Err(ErrorCode::SeriesHasEarlyExercises.into());
+        }
+
+        let mint_seeds = mint_authority! {
+            bump = ctx.accounts.state.mint_auth_nonce
+        };
+
+        let collateral_min_lot_size: u64 = get_collateral_amount_per_option(
+            &ctx.accounts.option_account,
+            &ctx.accounts.collateral_mint,
+        );
+        assert!(collateral_amount % collateral_min_lot_size == 0);
+
+        let mint_amount = collateral_amount
+            .checked_div(collateral_min_lot_size)
+            .unwrap();
+
+        token::mint_to(
+            ctx.accounts
+                .into_mint_to_context()
+                .with_signer(&[&mint_seeds[..]]),
+            mint_amount,
+        )?;
+
+        token::mint_to(
+            ctx.accounts
+                .into_mint_writer_to_context()
+                .with_signer(&[&mint_seeds[..]]),
+            mint_amount,
+        )?;
+
+        token::transfer(ctx.accounts.into_transfer_context(), collateral_amount)?;
+
+        let mint_fee = get_fee(collateral_amount, ctx.accounts.state.mint_fee_bps);
+        if mint_fee > 0 {
+            token::transfer(ctx.accounts.into_fee_transfer_context(), mint_fee)?;
+        }
+
+        emit!(OptionsWritten {
+            option_account: ctx.accounts.option_account.key(),
+            writer: ctx.accounts.writer.key(),
+            collateral_amount,
+            option_amount: mint_amount,
+            mint_fee,
+        });
+
         Ok(())
     }
 
     // TODO can you only burn the whole amount?
+    // Nets option tokens against writer tokens held by the same authority
+    // and returns the matching collateral.
     pub fn burn_option(ctx: Context<BurnOption>, amount: u64) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).burn {
+            return Err(ErrorCode::BurningPaused.into());
+        }
+
         let clock = Clock::get()?;
 
         if clock.unix_timestamp > ctx.accounts.option_account.expiry as i64 {
             return Err(ErrorCode::CannotBurnOptionsAfterExpiry.into());
         }
 
-        if ctx.accounts.option_account.settlement_price != 0 {
-            return Err(ErrorCode::CannotBurnOptionsAfterSettlementPriceIsSet.into());
+        match ctx.accounts.option_account.status {
+            OptionStatus::Active => {}
+            OptionStatus::Settling => return Err(ErrorCode::OptionIsSettling.into()),
+            OptionStatus::Settled | OptionStatus::Disputed => {
+                return Err(ErrorCode::CannotBurnOptionsAfterSettlementPriceIsSet.into());
+            }
+            OptionStatus::Closed => return Err(ErrorCode::OptionIsClosed.into()),
         }
 
         let mint_seeds = mint_authority! {
@@ -114,12 +540,26 @@ pub mod zeta_otc {
             bump = ctx.accounts.state.vault_auth_nonce
         };
 
-        let underlying_min_lot_size: u64 = 10u64
-            .pow(ctx.accounts.underlying_mint.decimals.into())
-            .checked_div(10u64.pow(OPTION_MINT_DECIMALS.into()))
-            .unwrap();
-
-        let underlying_amount = amount.checked_mul(underlying_min_lot_size).unwrap();
+        // Early exercises leave each writer token backed by less than a full
+        // lot, so American writers burn out of their pro rata vault share.
+        let collateral_amount =
+            if ctx.accounts.option_account.exercise_style == ExerciseStyle::American {
+                // Only reachable with a zero amount, which has no share.
+                if ctx.accounts.writer_mint.supply == 0 {
+                    return Err(ErrorCode::InsufficientWriterTokens.into());
+                }
+                (ctx.accounts.vault.amount as u128)
+                    .checked_mul(amount as u128)
+                    .unwrap()
+                    .checked_div(ctx.accounts.writer_mint.supply as u128)
+                    .unwrap() as u64
+            } else {
+                let collateral_min_lot_size: u64 = get_collateral_amount_per_option(
+                    &ctx.accounts.option_account,
+                    &ctx.accounts.collateral_mint,
+                );
+                amount.checked_mul(collateral_min_lot_size).unwrap()
+            };
 
         token::burn(
             ctx.accounts
@@ -128,13 +568,27 @@ pub mod zeta_otc {
             amount,
         )?;
 
+        token::burn(
+            ctx.accounts
+                .into_burn_writer_context()
+                .with_signer(&[&mint_seeds[..]]),
+            amount,
+        )?;
+
         token::transfer(
             ctx.accounts
                 .into_transfer_context()
                 .with_signer(&[&vault_seeds[..]]),
-            underlying_amount,
+            collateral_amount,
         )?;
 
+        emit!(OptionsBurned {
+            option_account: ctx.accounts.option_account.key(),
+            authority: ctx.accounts.authority.key(),
+            option_amount: amount,
+            collateral_amount,
+        });
+
         Ok(())
     }
 
@@ -159,18 +613,97 @@ pub mod zeta_otc {
             return Err(ErrorCode::AfterSetSettlementPriceTime.into());
         }
 
+        // Once a price is set, only dispute_settlement can correct it.
+        match option_account.status {
+            OptionStatus::Active | OptionStatus::Settling => {}
+            OptionStatus::Settled | OptionStatus::Disputed => {
+                msg!(
+                    "Settlement price set at {}",
+                    option_account.settlement_price
+                );
+                return Err(ErrorCode::SettlementPriceAlreadySet.into());
+            }
+            OptionStatus::Closed => return Err(ErrorCode::OptionIsClosed.into()),
+        }
+
         ctx.accounts.option_account.settlement_price = override_price;
+        ctx.accounts.option_account.settlement_ts = clock.unix_timestamp as u64;
+        ctx.accounts.option_account.status = OptionStatus::Settled;
+        set_profit_and_remaining_collateral(
+            &mut ctx.accounts.option_account,
+            ctx.accounts.vault.amount,
+            &ctx.accounts.collateral_mint,
+            ctx.accounts.option_mint.supply,
+        )?;
+
+        emit!(OptionSettled {
+            option_account: ctx.accounts.option_account.key(),
+            method: SettlementMethod::Override,
+            settlement_source: ctx.accounts.option_account.settlement_source,
+            settlement_price: override_price,
+            confidence_bps: 0,
+            profit_per_option: ctx.accounts.option_account.profit_per_option,
+        });
+
+        Ok(())
+    }
+
+    // Replaces a bad settlement price while payouts are still locked. The
+    // window isn't extended, so a correction can't hold funds indefinitely.
+    pub fn dispute_settlement(
+        ctx: Context<DisputeSettlement>,
+        corrected_price: u64,
+    ) -> ProgramResult {
+        match ctx.accounts.option_account.status {
+            OptionStatus::Settled | OptionStatus::Disputed => {}
+            OptionStatus::Active | OptionStatus::Settling => {
+                return Err(ErrorCode::SettlementPriceNotSet.into());
+            }
+            OptionStatus::Closed => return Err(ErrorCode::OptionIsClosed.into()),
+        }
+
+        let clock = Clock::get()?;
+        if clock.unix_timestamp
+            >= get_dispute_end(&ctx.accounts.option_account, &ctx.accounts.state)
+        {
+            return Err(ErrorCode::SettlementDisputeWindowClosed.into());
+        }
+
+        msg!(
+            "Settlement price {} corrected to {}",
+            ctx.accounts.option_account.settlement_price,
+            corrected_price
+        );
+        ctx.accounts.option_account.settlement_price = corrected_price;
+        ctx.accounts.option_account.status = OptionStatus::Disputed;
         set_profit_and_remaining_collateral(
             &mut ctx.accounts.option_account,
             ctx.accounts.vault.amount,
-            &ctx.accounts.underlying_mint,
+            &ctx.accounts.collateral_mint,
             ctx.accounts.option_mint.supply,
         )?;
 
+        emit!(OptionSettled {
+            option_account: ctx.accounts.option_account.key(),
+            method: SettlementMethod::Dispute,
+            settlement_source: ctx.accounts.option_account.settlement_source,
+            settlement_price: corrected_price,
+            confidence_bps: 0,
+            profit_per_option: ctx.accounts.option_account.profit_per_option,
+        });
+
         Ok(())
     }
 
-    pub fn expire_option(ctx: Context<ExpireOption>) -> ProgramResult {
+    // The underlying's oracles after the primary one are passed in
+    // `remaining_accounts`, in the order they were added.
+    pub fn expire_option<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExpireOption<'info>>,
+    ) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).settlement {
+            return Err(ErrorCode::SettlementPaused.into());
+        }
+
         let clock = Clock::get()?;
         let option_account = &ctx.accounts.option_account;
 
@@ -203,175 +736,1283 @@ pub mod zeta_otc {
         }
 
         // Cannot expire more than once.
-        if ctx.accounts.option_account.settlement_price != 0 {
-            msg!(
-                "Settlement price set at {}",
-                ctx.accounts.option_account.settlement_price
-            );
-            return Err(ErrorCode::SettlementPriceAlreadySet.into());
+        match ctx.accounts.option_account.status {
+            OptionStatus::Active => {}
+            OptionStatus::Settling => return Err(ErrorCode::OptionIsSettling.into()),
+            OptionStatus::Settled | OptionStatus::Disputed => {
+                msg!(
+                    "Settlement price set at {}",
+                    ctx.accounts.option_account.settlement_price
+                );
+                return Err(ErrorCode::SettlementPriceAlreadySet.into());
+            }
+            OptionStatus::Closed => return Err(ErrorCode::OptionIsClosed.into()),
+        }
+
+        if ctx
+            .accounts
+            .option_account
+            .settlement_source
+            .uses_observations()
+        {
+            return Err(ErrorCode::SettlementSourceRequiresObservations.into());
         }
 
-        let oracle_price = get_oracle_price(&ctx.accounts.oracle) as u64;
+        let mut oracles = vec![ctx.accounts.oracle.clone()];
+        oracles.extend(ctx.remaining_accounts.iter().cloned());
+        let (oracle_price, confidence_bps) = get_median_oracle_price(
+            &ctx.accounts.underlying,
+            &oracles,
+            ctx.accounts.option_account.settlement_source,
+            clock.slot,
+        )?;
         ctx.accounts.option_account.settlement_price = oracle_price;
+        ctx.accounts.option_account.settlement_ts = clock.unix_timestamp as u64;
+        ctx.accounts.option_account.status = OptionStatus::Settled;
         set_profit_and_remaining_collateral(
             &mut ctx.accounts.option_account,
             ctx.accounts.vault.amount,
-            &ctx.accounts.underlying_mint,
+            &ctx.accounts.collateral_mint,
             ctx.accounts.option_mint.supply,
         )?;
+
+        emit!(OptionSettled {
+            option_account: ctx.accounts.option_account.key(),
+            method: SettlementMethod::Oracle,
+            settlement_source: ctx.accounts.option_account.settlement_source,
+            settlement_price: oracle_price,
+            confidence_bps,
+            profit_per_option: ctx.accounts.option_account.profit_per_option,
+        });
+
         Ok(())
     }
 
-    pub fn exercise_option(ctx: Context<ExerciseOption>) -> ProgramResult {
-        if ctx.accounts.option_account.settlement_price == 0 {
-            return Err(ErrorCode::SettlementPriceNotSet.into());
+    pub fn initialize_settlement_observations(
+        ctx: Context<InitializeSettlementObservations>,
+        settlement_observations_nonce: u8,
+    ) -> ProgramResult {
+        if !ctx
+            .accounts
+            .option_account
+            .settlement_source
+            .uses_observations()
+        {
+            return Err(ErrorCode::SettlementSourceDoesNotUseObservations.into());
         }
 
-        let clock = Clock::get()?;
-        if clock.unix_timestamp < ctx.accounts.option_account.expiry as i64 {
-            return Err(ErrorCode::OptionHasNotExpiredYet.into());
+        let settlement_observations = &mut ctx.accounts.settlement_observations;
+        settlement_observations.settlement_observations_nonce = settlement_observations_nonce;
+        settlement_observations.option_account = ctx.accounts.option_account.key();
+        Ok(())
+    }
+
+    // Permissionless, keepers sample the oracle throughout the settlement
+    // window so the option can settle on their mean or median.
+    pub fn record_settlement_observation(
+        ctx: Context<RecordSettlementObservation>,
+    ) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).settlement {
+            return Err(ErrorCode::SettlementPaused.into());
         }
 
-        let total_profit = ctx
+        // Otherwise a single sample would leave a spot or TWAP option stuck
+        // in Settling.
+        if !ctx
             .accounts
             .option_account
-            .profit_per_option
-            .checked_mul(ctx.accounts.user_option_token_account.amount)
-            .unwrap();
+            .settlement_source
+            .uses_observations()
+        {
+            return Err(ErrorCode::SettlementSourceDoesNotUseObservations.into());
+        }
 
-        let vault_seeds = vault_authority! {
-            bump = ctx.accounts.state.vault_auth_nonce
-        };
+        let clock = Clock::get()?;
+        let option_account = &ctx.accounts.option_account;
 
-        // Transfer what remains of the vault account into the creator's token account.
-        token::transfer(
-            ctx.accounts
-                .into_transfer_context()
-                .with_signer(&[&vault_seeds[..]]),
-            total_profit,
-        )?;
+        let start = option_account
+            .expiry
+            .checked_sub(ctx.accounts.state.settlement_price_threshold_seconds.into())
+            .unwrap();
 
-        token::burn(
-            ctx.accounts.into_burn_context(),
-            ctx.accounts.user_option_token_account.amount,
-        )?;
+        let end = option_account
+            .expiry
+            .checked_add(ctx.accounts.state.settlement_price_threshold_seconds.into())
+            .unwrap();
 
-        Ok(())
-    }
+        if clock.unix_timestamp < start as i64 {
+            return Err(ErrorCode::BeforeSetSettlementPriceTime.into());
+        }
 
-    pub fn collect_remaining_collateral(ctx: Context<CollectRemainingCollateral>) -> ProgramResult {
-        if ctx.accounts.option_account.settlement_price == 0 {
-            return Err(ErrorCode::SettlementPriceNotSet.into());
+        if clock.unix_timestamp > end as i64 {
+            return Err(ErrorCode::AfterSetSettlementPriceTime.into());
         }
 
-        let clock = Clock::get()?;
-        if clock.unix_timestamp < ctx.accounts.option_account.expiry as i64 {
-            return Err(ErrorCode::OptionHasNotExpiredYet.into());
+        match option_account.status {
+            OptionStatus::Active | OptionStatus::Settling => {}
+            OptionStatus::Settled | OptionStatus::Disputed => {
+                return Err(ErrorCode::SettlementPriceAlreadySet.into());
+            }
+            OptionStatus::Closed => return Err(ErrorCode::OptionIsClosed.into()),
         }
 
-        let vault_seeds = vault_authority! {
-            bump = ctx.accounts.state.vault_auth_nonce
-        };
+        let settlement_observations = &mut ctx.accounts.settlement_observations;
+        if settlement_observations.prices.len() >= MAX_SETTLEMENT_OBSERVATIONS {
+            return Err(ErrorCode::TooManySettlementObservations.into());
+        }
 
-        // Transfer what remains of the vault account into the creator's token account.
-        token::transfer(
-            ctx.accounts
-                .into_transfer_context()
-                .with_signer(&[&vault_seeds[..]]),
-            ctx.accounts.option_account.remaining_collateral,
+        // One sample per slot, so a single keeper can't stack the window.
+        if !settlement_observations.prices.is_empty()
+            && settlement_observations.last_observation_slot >= clock.slot
+        {
+            return Err(ErrorCode::SettlementObservationAlreadyRecorded.into());
+        }
+
+        check_oracle_is_live(
+            &ctx.accounts.oracle,
+            ctx.accounts.underlying.max_staleness_slots,
+            clock.slot,
         )?;
-        token::close_account(ctx.accounts.into_close_user_token_account())?;
 
-        ctx.accounts.option_account.remaining_collateral = 0;
+        let (oracle_price, oracle_conf) = get_oracle_price(&ctx.accounts.oracle)?;
+        if get_ratio_bps(oracle_conf as u128, oracle_price as u128)
+            > ctx.accounts.underlying.max_confidence_bps
+        {
+            return Err(ErrorCode::OracleConfidenceTooWide.into());
+        }
 
+        settlement_observations.prices.push(oracle_price);
+        settlement_observations.last_observation_slot = clock.slot;
+        ctx.accounts.option_account.status = OptionStatus::Settling;
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(args: InitializeStateArgs)]
-pub struct InitializeState<'info> {
-    #[account(
-        init,
-        seeds = [STATE_SEED.as_bytes().as_ref()],
-        bump = args.state_nonce,
-        payer = admin,
+    pub fn expire_option_from_observations(
+        ctx: Context<ExpireOptionFromObservations>,
+    ) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).settlement {
+            return Err(ErrorCode::SettlementPaused.into());
+        }
+
+        // Samples can be recorded until the window closes, settling earlier
+        // would let a single keeper skip the half after expiry.
+        let clock = Clock::get()?;
+        let end = ctx
+            .accounts
+            .option_account
+            .expiry
+            .checked_add(ctx.accounts.state.settlement_price_threshold_seconds.into())
+            .unwrap();
+        if clock.unix_timestamp <= end as i64 {
+            msg!(
+                "Current time {} <= Observation window end {}",
+                clock.unix_timestamp,
+                end
+            );
+            return Err(ErrorCode::SettlementObservationWindowOpen.into());
+        }
+
+        // Recording the first observation moves the option into Settling.
+        match ctx.accounts.option_account.status {
+            OptionStatus::Settling => {}
+            OptionStatus::Active => return Err(ErrorCode::NoSettlementObservations.into()),
+            OptionStatus::Settled | OptionStatus::Disputed => {
+                return Err(ErrorCode::SettlementPriceAlreadySet.into());
+            }
+            OptionStatus::Closed => return Err(ErrorCode::OptionIsClosed.into()),
+        }
+
+        let prices = &ctx.accounts.settlement_observations.prices;
+        if prices.is_empty() {
+            return Err(ErrorCode::NoSettlementObservations.into());
+        }
+
+        let settlement_price = match ctx.accounts.option_account.settlement_source {
+            SettlementSource::ObservationMean => get_mean_price(prices),
+            SettlementSource::ObservationMedian => get_median_price(prices),
+            SettlementSource::Spot | SettlementSource::Twap => {
+                return Err(ErrorCode::SettlementSourceDoesNotUseObservations.into());
+            }
+        };
+
+        ctx.accounts.option_account.settlement_price = settlement_price;
+        ctx.accounts.option_account.settlement_ts = clock.unix_timestamp as u64;
+        ctx.accounts.option_account.status = OptionStatus::Settled;
+        set_profit_and_remaining_collateral(
+            &mut ctx.accounts.option_account,
+            ctx.accounts.vault.amount,
+            &ctx.accounts.collateral_mint,
+            ctx.accounts.option_mint.supply,
+        )?;
+
+        emit!(OptionSettled {
+            option_account: ctx.accounts.option_account.key(),
+            method: SettlementMethod::Observations,
+            settlement_source: ctx.accounts.option_account.settlement_source,
+            settlement_price,
+            confidence_bps: 0,
+            profit_per_option: ctx.accounts.option_account.profit_per_option,
+        });
+
+        Ok(())
+    }
+
+    pub fn exercise_option(ctx: Context<ExerciseOption>) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).exercise {
+            return Err(ErrorCode::ExercisePaused.into());
+        }
+
+        // Once a settlement price is set, holders wait for expiry and the
+        // dispute window like European holders.
+        let clock = Clock::get()?;
+        let early_exercise = ctx.accounts.option_account.exercise_style == ExerciseStyle::American
+            && clock.unix_timestamp < ctx.accounts.option_account.expiry as i64
+            && matches!(
+                ctx.accounts.option_account.status,
+                OptionStatus::Active | OptionStatus::Settling
+            );
+
+        let profit_per_option = if early_exercise {
+            // American options exercise against the live oracle price, held
+            // to the same liveness, staleness and confidence checks as
+            // settlement. The burn and vault transfer below shrink the
+            // supply and collateral that settlement is later computed from.
+            let (oracle_price, _) = get_fresh_oracle_price(
+                &ctx.accounts.oracle,
+                ctx.accounts.underlying.oracle_types[0],
+                SettlementSource::Spot,
+                &ctx.accounts.underlying,
+                clock.slot,
+            )?;
+            let profit_per_option = get_profit_per_option(
+                &ctx.accounts.option_account,
+                oracle_price,
+                &ctx.accounts.collateral_mint,
+            );
+            if profit_per_option == 0 {
+                return Err(ErrorCode::OptionIsOutOfTheMoney.into());
+            }
+            profit_per_option
+        } else {
+            check_option_is_settled(&ctx.accounts.option_account)?;
+
+            if clock.unix_timestamp < ctx.accounts.option_account.expiry as i64 {
+                return Err(ErrorCode::OptionHasNotExpiredYet.into());
+            }
+
+            if clock.unix_timestamp
+                < get_dispute_end(&ctx.accounts.option_account, &ctx.accounts.state)
+            {
+                return Err(ErrorCode::SettlementDisputeWindowActive.into());
+            }
+
+            if ctx.accounts.option_account.settlement_mode == SettlementMode::Physical
+                && clock.unix_timestamp
+                    >= get_exercise_deadline(&ctx.accounts.option_account, &ctx.accounts.state)
+            {
+                return Err(ErrorCode::ExercisePeriodOver.into());
+            }
+            ctx.accounts.option_account.profit_per_option
+        };
+
+        let total_profit = profit_per_option
+            .checked_mul(ctx.accounts.user_option_token_account.amount)
+            .unwrap();
+
+        // Physically settled holders pay the other leg of the trade into the
+        // settlement vault before receiving the collateral lot.
+        if ctx.accounts.option_account.settlement_mode == SettlementMode::Physical
+            && total_profit > 0
+        {
+            let total_payment = get_exercise_payment_per_option(
+                &ctx.accounts.option_account,
+                &ctx.accounts.settlement_mint,
+            )
+            .checked_mul(ctx.accounts.user_option_token_account.amount)
+            .unwrap();
+
+            token::transfer(
+                ctx.accounts.into_settlement_transfer_context(),
+                total_payment,
+            )?;
+        }
+
+        let vault_seeds = vault_authority! {
+            bump = ctx.accounts.state.vault_auth_nonce
+        };
+
+        let exercise_fee = get_fee(total_profit, ctx.accounts.state.exercise_fee_bps);
+        if exercise_fee > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_fee_transfer_context()
+                    .with_signer(&[&vault_seeds[..]]),
+                exercise_fee,
+            )?;
+        }
+
+        // Transfer what remains of the vault account into the creator's token account.
+        token::transfer(
+            ctx.accounts
+                .into_transfer_context()
+                .with_signer(&[&vault_seeds[..]]),
+            total_profit.checked_sub(exercise_fee).unwrap(),
+        )?;
+
+        token::burn(
+            ctx.accounts.into_burn_context(),
+            ctx.accounts.user_option_token_account.amount,
+        )?;
+
+        emit!(OptionExercised {
+            option_account: ctx.accounts.option_account.key(),
+            holder: ctx.accounts.authority.key(),
+            option_amount: ctx.accounts.user_option_token_account.amount,
+            payout: total_profit.checked_sub(exercise_fee).unwrap(),
+            exercise_fee,
+            early: early_exercise,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless crank that exercises every holder passed in
+    // `remaining_accounts` as (option token account, collateral token account)
+    // pairs. Holders opt in by delegating their option tokens to the mint
+    // authority, the rest are skipped so one holder can't stall the batch.
+    pub fn auto_exercise<'info>(
+        ctx: Context<'_, '_, '_, 'info, AutoExercise<'info>>,
+    ) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).exercise {
+            return Err(ErrorCode::ExercisePaused.into());
+        }
+
+        check_option_is_settled(&ctx.accounts.option_account)?;
+
+        let clock = Clock::get()?;
+        if clock.unix_timestamp < ctx.accounts.option_account.expiry as i64 {
+            return Err(ErrorCode::OptionHasNotExpiredYet.into());
+        }
+
+        if clock.unix_timestamp < get_dispute_end(&ctx.accounts.option_account, &ctx.accounts.state)
+        {
+            return Err(ErrorCode::SettlementDisputeWindowActive.into());
+        }
+
+        // Physical exercise needs the holder to pay, which a crank can't do.
+        if ctx.accounts.option_account.settlement_mode != SettlementMode::Cash {
+            return Err(ErrorCode::AutoExerciseRequiresCashSettlement.into());
+        }
+
+        if ctx.remaining_accounts.len() % 2 != 0 {
+            return Err(ErrorCode::InvalidAutoExerciseAccounts.into());
+        }
+
+        let mint_seeds = mint_authority! {
+            bump = ctx.accounts.state.mint_auth_nonce
+        };
+
+        let vault_seeds = vault_authority! {
+            bump = ctx.accounts.state.vault_auth_nonce
+        };
+
+        let mut total_exercise_fee: u64 = 0;
+        for accounts in ctx.remaining_accounts.chunks(2) {
+            let user_option_token_account: Account<TokenAccount> = Account::try_from(&accounts[0])?;
+            let collateral_token_account: Account<TokenAccount> = Account::try_from(&accounts[1])?;
+
+            if user_option_token_account.mint != ctx.accounts.option_mint.key()
+                || collateral_token_account.mint != ctx.accounts.collateral_mint.key()
+            {
+                return Err(ErrorCode::TokenAccountMintMismatch.into());
+            }
+
+            if collateral_token_account.owner != user_option_token_account.owner {
+                return Err(ErrorCode::OwnerMismatch.into());
+            }
+
+            let amount = user_option_token_account.amount;
+            if amount == 0 {
+                continue;
+            }
+
+            if user_option_token_account.delegate
+                != COption::Some(ctx.accounts.mint_authority.key())
+                || user_option_token_account.delegated_amount < amount
+            {
+                continue;
+            }
+
+            let total_profit = ctx
+                .accounts
+                .option_account
+                .profit_per_option
+                .checked_mul(amount)
+                .unwrap();
+            let exercise_fee = get_fee(total_profit, ctx.accounts.state.exercise_fee_bps);
+            total_exercise_fee = total_exercise_fee.checked_add(exercise_fee).unwrap();
+
+            token::transfer(
+                ctx.accounts
+                    .into_transfer_context(accounts[1].clone())
+                    .with_signer(&[&vault_seeds[..]]),
+                total_profit.checked_sub(exercise_fee).unwrap(),
+            )?;
+
+            token::burn(
+                ctx.accounts
+                    .into_burn_context(accounts[0].clone())
+                    .with_signer(&[&mint_seeds[..]]),
+                amount,
+            )?;
+
+            emit!(OptionExercised {
+                option_account: ctx.accounts.option_account.key(),
+                holder: user_option_token_account.owner,
+                option_amount: amount,
+                payout: total_profit.checked_sub(exercise_fee).unwrap(),
+                exercise_fee,
+                early: false,
+            });
+        }
+
+        if total_exercise_fee > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_fee_transfer_context()
+                    .with_signer(&[&vault_seeds[..]]),
+                total_exercise_fee,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn collect_remaining_collateral(ctx: Context<CollectRemainingCollateral>) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).burn {
+            return Err(ErrorCode::BurningPaused.into());
+        }
+
+        check_option_is_settled(&ctx.accounts.option_account)?;
+
+        let clock = Clock::get()?;
+        if clock.unix_timestamp < ctx.accounts.option_account.expiry as i64 {
+            return Err(ErrorCode::OptionHasNotExpiredYet.into());
+        }
+
+        if clock.unix_timestamp < get_dispute_end(&ctx.accounts.option_account, &ctx.accounts.state)
+        {
+            return Err(ErrorCode::SettlementDisputeWindowActive.into());
+        }
+
+        // In the money physical options reserve their collateral until every
+        // holder has paid for and taken delivery of it. Once the exercise
+        // period is over nothing else can leave the vault, so the reserve of
+        // lapsed options joins the writers' pool.
+        if ctx.accounts.option_account.settlement_mode == SettlementMode::Physical
+            && ctx.accounts.option_account.profit_per_option != 0
+        {
+            if clock.unix_timestamp
+                >= get_exercise_deadline(&ctx.accounts.option_account, &ctx.accounts.state)
+            {
+                ctx.accounts.option_account.remaining_collateral = ctx.accounts.vault.amount;
+            } else if ctx.accounts.option_mint.supply != 0 {
+                return Err(ErrorCode::PhysicalOptionsNotFullyExercised.into());
+            }
+        }
+
+        let vault_seeds = vault_authority! {
+            bump = ctx.accounts.state.vault_auth_nonce
+        };
+
+        // Writer tokens are redeemed for their share of the pool. Burning
+        // them while shrinking the pool keeps the ratio intact for the
+        // holders that redeem later.
+        let writer_tokens = ctx.accounts.user_writer_token_account.amount;
+        let writer_supply = ctx.accounts.writer_mint.supply;
+        let collateral_share = get_writer_share(
+            ctx.accounts.option_account.remaining_collateral,
+            writer_tokens,
+            writer_supply,
+        );
+        let settlement_share = get_writer_share(
+            ctx.accounts.settlement_vault.amount,
+            writer_tokens,
+            writer_supply,
+        );
+
+        token::burn(ctx.accounts.into_burn_writer_context(), writer_tokens)?;
+
+        let settlement_fee = get_fee(collateral_share, ctx.accounts.state.settlement_fee_bps);
+        if settlement_fee > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_fee_transfer_context()
+                    .with_signer(&[&vault_seeds[..]]),
+                settlement_fee,
+            )?;
+        }
+
+        // Transfer the writer's share of the vault account into their token account.
+        token::transfer(
+            ctx.accounts
+                .into_transfer_context()
+                .with_signer(&[&vault_seeds[..]]),
+            collateral_share.checked_sub(settlement_fee).unwrap(),
+        )?;
+
+        // Transfer the exercise proceeds of physically settled options.
+        token::transfer(
+            ctx.accounts
+                .into_settlement_transfer_context()
+                .with_signer(&[&vault_seeds[..]]),
+            settlement_share,
+        )?;
+
+        let option_account = &mut ctx.accounts.option_account;
+        option_account.remaining_collateral = option_account
+            .remaining_collateral
+            .checked_sub(collateral_share)
+            .unwrap();
+
+        // The last writer out of a series with no open options closes it.
+        if writer_tokens == writer_supply && ctx.accounts.option_mint.supply == 0 {
+            option_account.status = OptionStatus::Closed;
+        }
+
+        emit!(CollateralCollected {
+            option_account: ctx.accounts.option_account.key(),
+            writer: ctx.accounts.writer.key(),
+            writer_tokens,
+            collateral_amount: collateral_share.checked_sub(settlement_fee).unwrap(),
+            settlement_amount: settlement_share,
+            settlement_fee,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless, returns the rent of a finished series to its creator.
+    // A series with no options or writer tokens left can be closed at any
+    // time. Otherwise it must be settled and can only be closed once the
+    // claim period has passed, sweeping the leftovers to the treasury.
+    // Series settling on observations pass the observations account first in
+    // `remaining_accounts`, and its rent also goes to the creator.
+    pub fn close_option<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseOption<'info>>,
+    ) -> ProgramResult {
+        if get_pause_flags(&ctx.accounts.state, &ctx.accounts.underlying).burn {
+            return Err(ErrorCode::BurningPaused.into());
+        }
+
+        let fully_claimed =
+            ctx.accounts.option_mint.supply == 0 && ctx.accounts.writer_mint.supply == 0;
+        if !fully_claimed {
+            match ctx.accounts.option_account.status {
+                OptionStatus::Settled | OptionStatus::Disputed | OptionStatus::Closed => {}
+                OptionStatus::Active | OptionStatus::Settling => {
+                    return Err(ErrorCode::SettlementPriceNotSet.into());
+                }
+            }
+
+            let clock = Clock::get()?;
+            let dispute_end = get_dispute_end(&ctx.accounts.option_account, &ctx.accounts.state);
+            if clock.unix_timestamp < dispute_end {
+                return Err(ErrorCode::SettlementDisputeWindowActive.into());
+            }
+
+            // An early settlement doesn't shorten the claim period.
+            let claim_deadline = get_claim_start(&ctx.accounts.option_account, &ctx.accounts.state)
+                .checked_add(ctx.accounts.state.claim_period_seconds.into())
+                .unwrap();
+            if clock.unix_timestamp < claim_deadline {
+                msg!(
+                    "Current time {} < Claim deadline {}",
+                    clock.unix_timestamp,
+                    claim_deadline
+                );
+                return Err(ErrorCode::ClaimPeriodActive.into());
+            }
+        }
+
+        if ctx
+            .accounts
+            .option_account
+            .settlement_source
+            .uses_observations()
+        {
+            let (settlement_observations_address, _) = Pubkey::find_program_address(
+                &[
+                    SETTLEMENT_OBSERVATIONS_SEED.as_bytes(),
+                    ctx.accounts.option_account.key().as_ref(),
+                ],
+                ctx.program_id,
+            );
+            let settlement_observations_info = match ctx.remaining_accounts.first() {
+                Some(info) if info.key() == settlement_observations_address => info,
+                _ => return Err(ErrorCode::InvalidSettlementObservationsAccount.into()),
+            };
+            // Keepers may never have initialized it.
+            if settlement_observations_info.owner == ctx.program_id {
+                let settlement_observations =
+                    Account::<SettlementObservations>::try_from(settlement_observations_info)?;
+                settlement_observations.close(ctx.accounts.creator.to_account_info())?;
+            }
+        }
+
+        let vault_seeds = vault_authority! {
+            bump = ctx.accounts.state.vault_auth_nonce
+        };
+
+        // Rounding dust or unclaimed payouts.
+        if ctx.accounts.vault.amount > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_sweep_context()
+                    .with_signer(&[&vault_seeds[..]]),
+                ctx.accounts.vault.amount,
+            )?;
+        }
+
+        if ctx.accounts.settlement_vault.amount > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_settlement_sweep_context()
+                    .with_signer(&[&vault_seeds[..]]),
+                ctx.accounts.settlement_vault.amount,
+            )?;
+        }
+
+        token::close_account(
+            ctx.accounts
+                .into_close_vault_context()
+                .with_signer(&[&vault_seeds[..]]),
+        )?;
+
+        token::close_account(
+            ctx.accounts
+                .into_close_settlement_vault_context()
+                .with_signer(&[&vault_seeds[..]]),
+        )?;
+
+        emit!(OptionClosed {
+            option_account: ctx.accounts.option_account.key(),
+            creator: ctx.accounts.creator.key(),
+            swept_collateral: ctx.accounts.vault.amount,
+            swept_settlement: ctx.accounts.settlement_vault.amount,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(args: InitializeStateArgs)]
+pub struct InitializeState<'info> {
+    #[account(
+        init,
+        seeds = [STATE_SEED.as_bytes().as_ref()],
+        bump = args.state_nonce,
+        payer = admin,
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub system_program: Program<'info, System>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [MINT_AUTH_SEED.as_bytes().as_ref()],
+        bump = args.mint_auth_nonce,
+    )]
+    pub mint_authority: AccountInfo<'info>,
+    #[account(
+        seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
+        bump = args.vault_auth_nonce,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    pub quote_mint: Account<'info, Mint>,
+    pub guardian: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+    pub new_admin: UncheckedAccount<'info>,
+    #[account(
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+    #[account(
+        constraint = new_admin.key() == state.pending_admin @ ErrorCode::UnauthorizedPendingAdmin
+    )]
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRoles<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+    pub listing_authority: UncheckedAccount<'info>,
+    pub settlement_authority: UncheckedAccount<'info>,
+    pub guardian: UncheckedAccount<'info>,
+    #[account(
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(queued_action_nonce: u8)]
+pub struct QueueAdminAction<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+    #[account(
+        init,
+        seeds = [QUEUED_ACTION_SEED.as_bytes().as_ref(), &state.queued_action_count.to_le_bytes()],
+        bump = queued_action_nonce,
+        payer = admin,
+        space = 8 + 1 + 8 + 1 + 32 + 32 + 1 + 8,
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+    #[account(
+        mut,
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAdminAction<'info> {
+    pub state: Account<'info, State>,
+    #[account(
+        mut,
+        seeds = [QUEUED_ACTION_SEED.as_bytes().as_ref(), &queued_action.index.to_le_bytes()],
+        bump = queued_action.queued_action_nonce,
+        close = admin,
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+    #[account(
+        mut,
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAdminAction<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+    #[account(
+        mut,
+        seeds = [QUEUED_ACTION_SEED.as_bytes().as_ref(), &queued_action.index.to_le_bytes()],
+        bump = queued_action.queued_action_nonce,
+        close = admin,
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+    #[account(
+        mut,
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseFlags<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+    #[account(
+        constraint = guardian.key() == state.guardian @ ErrorCode::UnauthorizedGuardian
+    )]
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUnderlyingPauseFlags<'info> {
+    pub state: Account<'info, State>,
+    #[account(
+        mut,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying.mint.as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Account<'info, Underlying>,
+    #[account(
+        constraint = guardian.key() == state.guardian @ ErrorCode::UnauthorizedGuardian
+    )]
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(treasury_nonce: u8)]
+pub struct InitializeTreasury<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        init,
+        token::mint = mint,
+        token::authority = state,
+        seeds = [TREASURY_SEED.as_bytes().as_ref(), mint.key().as_ref()],
+        bump = treasury_nonce,
+        payer = admin,
+    )]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+    pub mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetFees<'info> {
+    #[account(mut)]
+    pub state: Account<'info, State>,
+    #[account(
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromTreasury<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        constraint = treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = destination.mint == treasury.mint @ ErrorCode::TokenAccountMintMismatch,
+    )]
+    pub destination: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = admin.key() == state.admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub admin: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: InitializeUnderlyingArgs)]
+pub struct InitializeUnderlying<'info> {
+    pub state: Account<'info, State>,
+    #[account(
+        init,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), mint.key().as_ref()],
+        bump = args.underlying_nonce,
+        payer = listing_authority,
+    )]
+    pub underlying: Account<'info, Underlying>,
+    pub mint: Account<'info, Mint>,
+    pub oracle: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub listing_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetOptionLimits<'info> {
+    pub state: Account<'info, State>,
+    #[account(
+        mut,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying.mint.as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Account<'info, Underlying>,
+    #[account(
+        constraint = listing_authority.key() == state.listing_authority @ ErrorCode::UnauthorizedListingAuthority
+    )]
+    pub listing_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetExpiryCalendar<'info> {
+    pub state: Account<'info, State>,
+    #[account(
+        mut,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying.mint.as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Account<'info, Underlying>,
+    #[account(
+        constraint = listing_authority.key() == state.listing_authority @ ErrorCode::UnauthorizedListingAuthority
+    )]
+    pub listing_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetNextValidExpiries<'info> {
+    #[account(
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying.mint.as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Account<'info, Underlying>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAdminPrice<'info> {
+    #[account(init, payer = authority)]
+    pub admin_price: Account<'info, AdminPrice>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdminPrice<'info> {
+    #[account(mut)]
+    pub admin_price: Account<'info, AdminPrice>,
+    #[account(
+        constraint = authority.key() == admin_price.authority @ ErrorCode::UnauthorizedPriceAuthority
+    )]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: InitializeOptionArgs)]
+pub struct InitializeOption<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Box<Account<'info, Underlying>>,
+    #[account(
+        constraint = oracle.key() == underlying.oracle @ ErrorCode::InvalidOracle
+    )]
+    pub oracle: AccountInfo<'info>,
+    #[account(
+        init,
+        token::mint = collateral_mint,
+        token::authority = vault_authority,
+        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = args.vault_nonce,
+        payer = creator,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
+        bump = state.vault_auth_nonce,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == args.kind.collateral_mint(underlying_mint.key(), state.quote_mint) @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = treasury.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = settlement_mint.key() == args.kind.settlement_mint(underlying_mint.key(), state.quote_mint) @ ErrorCode::InvalidSettlementMint
+    )]
+    pub settlement_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        token::mint = settlement_mint,
+        token::authority = vault_authority,
+        seeds = [SETTLEMENT_VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = args.settlement_vault_nonce,
+        payer = creator,
+    )]
+    pub settlement_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = collateral_token_account.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = collateral_token_account.owner == creator.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = collateral_token_account.amount >= get_amount_with_fee(args.collateral_amount, state.mint_fee_bps) @ ErrorCode::InsufficientFunds,
+    )]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(
+        init,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &underlying.count.to_le_bytes()],
+        bump = args.option_account_nonce,
+        payer = creator,
+    )]
+    pub option_account: Box<Account<'info, OptionAccount>>,
+    #[account(
+        seeds = [MINT_AUTH_SEED.as_bytes().as_ref()],
+        bump = state.mint_auth_nonce,
+    )]
+    pub mint_authority: AccountInfo<'info>,
+    #[account(
+        init,
+        mint::decimals = OPTION_MINT_DECIMALS,
+        mint::authority = mint_authority,
+        seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = args.option_mint_nonce,
+        payer = creator,
+    )]
+    pub option_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        token::mint = option_mint,
+        token::authority = creator,
+        seeds = [option_mint.key().as_ref(), creator.key().as_ref()],
+        bump = args.token_account_nonce,
+        payer = creator,
+    )]
+    pub user_option_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        mint::decimals = OPTION_MINT_DECIMALS,
+        mint::authority = mint_authority,
+        seeds = [WRITER_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = args.writer_mint_nonce,
+        payer = creator,
+    )]
+    pub writer_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        token::mint = writer_mint,
+        token::authority = creator,
+        seeds = [writer_mint.key().as_ref(), creator.key().as_ref()],
+        bump = args.writer_token_account_nonce,
+        payer = creator,
+    )]
+    pub user_writer_token_account: Box<Account<'info, TokenAccount>>,
+    // Created and checked by the metadata program.
+    #[account(mut)]
+    pub option_metadata: UncheckedAccount<'info>,
+    pub metadata_program: Program<'info, TokenMetadata>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireOption<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Box<Account<'info, Underlying>>,
+    pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
+        bump = option_account.option_account_nonce,
+    )]
+    pub option_account: Box<Account<'info, OptionAccount>>,
+    #[account(
+        constraint = oracle.key() == underlying.oracle @ ErrorCode::InvalidOracle
+    )]
+    pub oracle: AccountInfo<'info>,
+    #[account(
+        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::InvalidOracle
+    )]
+    pub option_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.vault_nonce,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireOptionOverride<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Box<Account<'info, Underlying>>,
+    pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
+        bump = option_account.option_account_nonce,
+    )]
+    pub option_account: Box<Account<'info, OptionAccount>>,
+    #[account(
+        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::InvalidOracle
+    )]
+    pub option_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.vault_nonce,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        constraint = settlement_authority.key() == state.settlement_authority @ ErrorCode::UnauthorizedSettlementAuthority
     )]
+    pub settlement_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeSettlement<'info> {
     pub state: Box<Account<'info, State>>,
-    pub system_program: Program<'info, System>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
     #[account(
-        seeds = [MINT_AUTH_SEED.as_bytes().as_ref()],
-        bump = args.mint_auth_nonce,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
+        bump = underlying.underlying_nonce,
     )]
-    pub mint_authority: AccountInfo<'info>,
+    pub underlying: Box<Account<'info, Underlying>>,
+    pub underlying_mint: Box<Account<'info, Mint>>,
     #[account(
-        seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
-        bump = args.vault_auth_nonce,
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
     )]
-    pub vault_authority: AccountInfo<'info>,
+    pub collateral_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
+        bump = option_account.option_account_nonce,
+    )]
+    pub option_account: Box<Account<'info, OptionAccount>>,
+    #[account(
+        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::OptionMintMismatch
+    )]
+    pub option_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.vault_nonce,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    #[account(
+        constraint = authority.key() == state.settlement_authority || authority.key() == state.guardian @ ErrorCode::UnauthorizedSettlementDisputer
+    )]
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(args: InitializeUnderlyingArgs)]
-pub struct InitializeUnderlying<'info> {
-    pub state: Account<'info, State>,
+#[instruction(settlement_observations_nonce: u8)]
+pub struct InitializeSettlementObservations<'info> {
     #[account(
         init,
-        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), mint.key().as_ref()],
-        bump = args.underlying_nonce,
-        payer = admin,
+        seeds = [SETTLEMENT_OBSERVATIONS_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = settlement_observations_nonce,
+        payer = payer,
+        space = 8 + 1 + 32 + 8 + 4 + 8 * MAX_SETTLEMENT_OBSERVATIONS,
     )]
-    pub underlying: Account<'info, Underlying>,
-    pub mint: Account<'info, Mint>,
-    pub oracle: UncheckedAccount<'info>,
+    pub settlement_observations: Box<Account<'info, SettlementObservations>>,
+    pub option_account: Box<Account<'info, OptionAccount>>,
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(args: InitializeOptionArgs)]
-pub struct InitializeOption<'info> {
+pub struct RecordSettlementObservation<'info> {
     pub state: Box<Account<'info, State>>,
+    #[account(
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
+        bump = underlying.underlying_nonce,
+    )]
+    pub underlying: Box<Account<'info, Underlying>>,
+    pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
+        bump = option_account.option_account_nonce,
+    )]
+    pub option_account: Box<Account<'info, OptionAccount>>,
     #[account(
         mut,
+        seeds = [SETTLEMENT_OBSERVATIONS_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = settlement_observations.settlement_observations_nonce,
+    )]
+    pub settlement_observations: Box<Account<'info, SettlementObservations>>,
+    #[account(
+        constraint = oracle.key() == underlying.oracle @ ErrorCode::InvalidOracle
+    )]
+    pub oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireOptionFromObservations<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
         seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
         bump = underlying.underlying_nonce,
     )]
     pub underlying: Box<Account<'info, Underlying>>,
+    pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
+        bump = option_account.option_account_nonce,
+    )]
+    pub option_account: Box<Account<'info, OptionAccount>>,
+    #[account(
+        seeds = [SETTLEMENT_OBSERVATIONS_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = settlement_observations.settlement_observations_nonce,
+    )]
+    pub settlement_observations: Box<Account<'info, SettlementObservations>>,
+    #[account(
+        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::OptionMintMismatch
+    )]
+    pub option_mint: Account<'info, Mint>,
     #[account(
-        init,
-        token::mint = underlying_mint,
-        token::authority = vault_authority,
         seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
-        bump = args.vault_nonce,
-        payer = creator,
+        bump = option_account.vault_nonce,
     )]
     pub vault: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64)]
+pub struct BurnOption<'info> {
+    pub state: Box<Account<'info, State>>,
     #[account(
-        seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
-        bump = state.vault_auth_nonce,
+        seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
+        bump = underlying.underlying_nonce,
     )]
-    pub vault_authority: AccountInfo<'info>,
+    pub underlying: Box<Account<'info, Underlying>>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.vault_nonce,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
     pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        constraint = underlying_token_account.mint == underlying_mint.key() @ ErrorCode::TokenAccountMintMismatch,
-        constraint = underlying_token_account.owner == creator.key() @ ErrorCode::InvalidTokenAccountOwner,
-        constraint = underlying_token_account.amount >= args.collateral_amount @ ErrorCode::InsufficientFunds,
+        constraint = collateral_token_account.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = collateral_token_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
     )]
-    pub underlying_token_account: Box<Account<'info, TokenAccount>>,
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
     #[account(mut)]
-    pub creator: Signer<'info>,
+    pub authority: Signer<'info>,
     #[account(
-        init,
-        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &underlying.count.to_le_bytes()],
-        bump = args.option_account_nonce,
-        payer = creator,
+        mut,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
+        bump = option_account.option_account_nonce,
     )]
     pub option_account: Box<Account<'info, OptionAccount>>,
     #[account(
@@ -380,38 +2021,92 @@ pub struct InitializeOption<'info> {
     )]
     pub mint_authority: AccountInfo<'info>,
     #[account(
-        init,
-        mint::decimals = OPTION_MINT_DECIMALS,
-        mint::authority = mint_authority,
+        mut,
         seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
-        bump = args.option_mint_nonce,
-        payer = creator,
+        bump = option_account.option_mint_nonce,
     )]
     pub option_mint: Box<Account<'info, Mint>>,
     #[account(
-        init,
-        token::mint = option_mint,
-        token::authority = creator,
-        seeds = [option_mint.key().as_ref(), creator.key().as_ref()],
-        bump = args.token_account_nonce,
-        payer = creator,
+        mut,
+        constraint = user_option_token_account.mint == option_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = user_option_token_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = user_option_token_account.amount >= amount @ ErrorCode::InsufficientOptionsToBurn,
     )]
     pub user_option_token_account: Box<Account<'info, TokenAccount>>,
-    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [WRITER_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.writer_mint_nonce,
+    )]
+    pub writer_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = user_writer_token_account.mint == writer_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = user_writer_token_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = user_writer_token_account.amount >= amount @ ErrorCode::InsufficientWriterTokens,
+    )]
+    pub user_writer_token_account: Box<Account<'info, TokenAccount>>,
     pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
+        bump = state.vault_auth_nonce,
+    )]
+    pub vault_authority: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ExpireOption<'info> {
+pub struct ExerciseOption<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
-        mut,
         seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
         bump = underlying.underlying_nonce,
     )]
     pub underlying: Box<Account<'info, Underlying>>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.vault_nonce,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
     pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = treasury.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = collateral_token_account.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = collateral_token_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = oracle.key() == underlying.oracle @ ErrorCode::InvalidOracle
+    )]
+    pub oracle: AccountInfo<'info>,
+    #[account(
+        constraint = settlement_mint.key() == option_account.settlement_mint @ ErrorCode::InvalidSettlementMint
+    )]
+    pub settlement_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [SETTLEMENT_VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.settlement_vault_nonce,
+    )]
+    pub settlement_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = settlement_token_account.mint == settlement_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = settlement_token_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub settlement_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
     #[account(
         mut,
         seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
@@ -419,54 +2114,97 @@ pub struct ExpireOption<'info> {
     )]
     pub option_account: Box<Account<'info, OptionAccount>>,
     #[account(
-        constraint = oracle.key() == underlying.oracle @ ErrorCode::InvalidOracle
+        mut,
+        seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.option_mint_nonce,
+        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::OptionMintMismatch
     )]
-    pub oracle: AccountInfo<'info>,
+    pub option_mint: Box<Account<'info, Mint>>,
     #[account(
-        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::InvalidOracle
+        mut,
+        constraint = user_option_token_account.owner == authority.key() @ ErrorCode::OwnerMismatch,
+        constraint = user_option_token_account.mint == option_mint.key() @ ErrorCode::TokenAccountMintMismatch,
     )]
-    pub option_mint: Account<'info, Mint>,
+    pub user_option_token_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
     #[account(
-        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
-        bump = option_account.vault_nonce,
+        seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
+        bump = state.vault_auth_nonce,
     )]
-    pub vault: Account<'info, TokenAccount>,
+    pub vault_authority: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ExpireOptionOverride<'info> {
+#[instruction(collateral_amount: u64)]
+pub struct WriteMoreOptions<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
-        mut,
         seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
         bump = underlying.underlying_nonce,
     )]
     pub underlying: Box<Account<'info, Underlying>>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.vault_nonce,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
     pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
+        constraint = treasury.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = collateral_token_account.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = collateral_token_account.owner == writer.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = collateral_token_account.amount >= get_amount_with_fee(collateral_amount, state.mint_fee_bps) @ ErrorCode::InsufficientFunds,
+    )]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+    pub writer: Signer<'info>,
+    #[account(
         seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
         bump = option_account.option_account_nonce,
     )]
     pub option_account: Box<Account<'info, OptionAccount>>,
     #[account(
-        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::InvalidOracle
+        seeds = [MINT_AUTH_SEED.as_bytes().as_ref()],
+        bump = state.mint_auth_nonce,
     )]
-    pub option_mint: Account<'info, Mint>,
+    pub mint_authority: AccountInfo<'info>,
     #[account(
-        seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
-        bump = option_account.vault_nonce,
+        mut,
+        seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.option_mint_nonce,
     )]
-    pub vault: Account<'info, TokenAccount>,
+    pub option_mint: Box<Account<'info, Mint>>,
     #[account(
-        constraint = admin.key() == state.admin @ ErrorCode::OnlyAdminCanOverrideSettlementPrice
+        mut,
+        constraint = user_option_token_account.mint == option_mint.key() @ ErrorCode::TokenAccountMintMismatch,
     )]
-    pub admin: Signer<'info>,
+    pub user_option_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [WRITER_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.writer_mint_nonce,
+    )]
+    pub writer_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = user_writer_token_account.mint == writer_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+    )]
+    pub user_writer_token_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64)]
-pub struct BurnOption<'info> {
+pub struct AutoExercise<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
         seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
@@ -481,26 +2219,20 @@ pub struct BurnOption<'info> {
     pub vault: Box<Account<'info, TokenAccount>>,
     pub underlying_mint: Box<Account<'info, Mint>>,
     #[account(
-        mut,
-        constraint = underlying_token_account.mint == underlying_mint.key() @ ErrorCode::TokenAccountMintMismatch,
-        constraint = underlying_token_account.owner == creator.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
     )]
-    pub underlying_token_account: Box<Account<'info, TokenAccount>>,
+    pub collateral_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        constraint = creator.key() == option_account.creator @ ErrorCode::OnlyCreatorCanBurnOptions
+        constraint = treasury.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
     )]
-    pub creator: Signer<'info>,
+    pub treasury: Box<Account<'info, TokenAccount>>,
     #[account(
         seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
         bump = option_account.option_account_nonce,
     )]
     pub option_account: Box<Account<'info, OptionAccount>>,
-    #[account(
-        seeds = [MINT_AUTH_SEED.as_bytes().as_ref()],
-        bump = state.mint_auth_nonce,
-    )]
-    pub mint_authority: AccountInfo<'info>,
     #[account(
         mut,
         seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
@@ -508,12 +2240,10 @@ pub struct BurnOption<'info> {
     )]
     pub option_mint: Box<Account<'info, Mint>>,
     #[account(
-        mut,
-        seeds = [option_mint.key().as_ref(), creator.key().as_ref()],
-        bump = option_account.creator_option_token_account_nonce,
-        constraint = user_option_token_account.amount >= amount @ ErrorCode::InsufficientOptionsToBurn,
+        seeds = [MINT_AUTH_SEED.as_bytes().as_ref()],
+        bump = state.mint_auth_nonce,
     )]
-    pub user_option_token_account: Box<Account<'info, TokenAccount>>,
+    pub mint_authority: AccountInfo<'info>,
     pub token_program: Program<'info, Token>,
     #[account(
         seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
@@ -523,7 +2253,7 @@ pub struct BurnOption<'info> {
 }
 
 #[derive(Accounts)]
-pub struct ExerciseOption<'info> {
+pub struct CollectRemainingCollateral<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
         seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
@@ -537,14 +2267,35 @@ pub struct ExerciseOption<'info> {
     )]
     pub vault: Box<Account<'info, TokenAccount>>,
     pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = collateral_mint.key() == option_account.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        constraint = underlying_token_account.mint == underlying_mint.key() @ ErrorCode::TokenAccountMintMismatch,
-        constraint = underlying_token_account.owner == authority.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = treasury.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
     )]
-    pub underlying_token_account: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    pub treasury: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = collateral_token_account.mint == collateral_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = collateral_token_account.owner == writer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub collateral_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [SETTLEMENT_VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.settlement_vault_nonce,
+    )]
+    pub settlement_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = settlement_token_account.mint == option_account.settlement_mint @ ErrorCode::TokenAccountMintMismatch,
+        constraint = settlement_token_account.owner == writer.key() @ ErrorCode::InvalidTokenAccountOwner,
+    )]
+    pub settlement_token_account: Box<Account<'info, TokenAccount>>,
+    pub writer: Signer<'info>,
     #[account(
         mut,
         seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
@@ -552,18 +2303,23 @@ pub struct ExerciseOption<'info> {
     )]
     pub option_account: Box<Account<'info, OptionAccount>>,
     #[account(
-        mut,
         seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
         bump = option_account.option_mint_nonce,
-        constraint = option_mint.key() == option_account.option_mint @ ErrorCode::OptionMintMismatch
     )]
     pub option_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        constraint = user_option_token_account.owner == authority.key() @ ErrorCode::OwnerMismatch,
-        constraint = user_option_token_account.mint == option_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        seeds = [WRITER_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.writer_mint_nonce,
     )]
-    pub user_option_token_account: Box<Account<'info, TokenAccount>>,
+    pub writer_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = user_writer_token_account.mint == writer_mint.key() @ ErrorCode::TokenAccountMintMismatch,
+        constraint = user_writer_token_account.owner == writer.key() @ ErrorCode::InvalidTokenAccountOwner,
+        constraint = user_writer_token_account.amount > 0 @ ErrorCode::InsufficientWriterTokens,
+    )]
+    pub user_writer_token_account: Box<Account<'info, TokenAccount>>,
     pub token_program: Program<'info, Token>,
     #[account(
         seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
@@ -573,49 +2329,60 @@ pub struct ExerciseOption<'info> {
 }
 
 #[derive(Accounts)]
-pub struct CollectRemainingCollateral<'info> {
+pub struct CloseOption<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
         seeds = [UNDERLYING_SEED.as_bytes().as_ref(), underlying_mint.key().as_ref()],
         bump = underlying.underlying_nonce,
     )]
     pub underlying: Box<Account<'info, Underlying>>,
+    pub underlying_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
+        bump = option_account.option_account_nonce,
+        close = creator,
+    )]
+    pub option_account: Box<Account<'info, OptionAccount>>,
     #[account(
         mut,
         seeds = [VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
         bump = option_account.vault_nonce,
     )]
     pub vault: Box<Account<'info, TokenAccount>>,
-    pub underlying_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        constraint = underlying_token_account.mint == underlying_mint.key() @ ErrorCode::TokenAccountMintMismatch,
-        constraint = underlying_token_account.owner == creator.key() @ ErrorCode::InvalidTokenAccountOwner,
+        seeds = [SETTLEMENT_VAULT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.settlement_vault_nonce,
     )]
-    pub underlying_token_account: Box<Account<'info, TokenAccount>>,
+    pub settlement_vault: Box<Account<'info, TokenAccount>>,
     #[account(
-        mut,
-        constraint = creator.key() == option_account.creator @ ErrorCode::OnlyCreatorCanCloseOptionAccount
+        seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.option_mint_nonce,
     )]
-    pub creator: Signer<'info>,
+    pub option_mint: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [WRITER_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
+        bump = option_account.writer_mint_nonce,
+    )]
+    pub writer_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        seeds = [OPTION_ACCOUNT_SEED.as_bytes().as_ref(), underlying.key().as_ref(), &option_account.underlying_count.to_le_bytes()],
-        bump = option_account.option_account_nonce,
+        constraint = treasury.mint == option_account.collateral_mint @ ErrorCode::TokenAccountMintMismatch,
+        constraint = treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
     )]
-    pub option_account: Box<Account<'info, OptionAccount>>,
+    pub treasury: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        seeds = [OPTION_MINT_SEED.as_bytes().as_ref(), option_account.key().as_ref()],
-        bump = option_account.option_mint_nonce,
+        constraint = settlement_treasury.mint == option_account.settlement_mint @ ErrorCode::TokenAccountMintMismatch,
+        constraint = settlement_treasury.owner == state.key() @ ErrorCode::InvalidTreasury,
     )]
-    pub option_mint: Box<Account<'info, Mint>>,
+    pub settlement_treasury: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        seeds = [option_mint.key().as_ref(), creator.key().as_ref()],
-        bump = option_account.creator_option_token_account_nonce,
+        constraint = creator.key() == option_account.creator @ ErrorCode::InvalidOptionCreator
     )]
-    pub user_option_token_account: Box<Account<'info, TokenAccount>>,
+    pub creator: AccountInfo<'info>,
     pub token_program: Program<'info, Token>,
     #[account(
         seeds = [VAULT_AUTH_SEED.as_bytes().as_ref()],
@@ -631,8 +2398,17 @@ pub struct InitializeOptionArgs {
     pub option_mint_nonce: u8,
     pub token_account_nonce: u8,
     pub vault_nonce: u8,
+    pub settlement_vault_nonce: u8,
+    pub writer_mint_nonce: u8,
+    pub writer_token_account_nonce: u8,
     pub expiry: u64,
     pub strike: u64,
+    // Strike of the short leg, only used by spreads.
+    pub short_strike: u64,
+    pub kind: OptionKind,
+    pub settlement_mode: SettlementMode,
+    pub exercise_style: ExerciseStyle,
+    pub settlement_source: SettlementSource,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -641,11 +2417,28 @@ pub struct InitializeStateArgs {
     pub mint_auth_nonce: u8,
     pub vault_auth_nonce: u8,
     pub settlement_price_threshold_seconds: u32,
+    pub settlement_dispute_seconds: u32,
+    pub admin_action_delay_seconds: u32,
+    pub claim_period_seconds: u32,
+    pub exercise_period_seconds: u32,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct InitializeUnderlyingArgs {
     pub underlying_nonce: u8,
+    // Ticker used to name option mints, e.g. SOL.
+    pub symbol: String,
+    pub max_confidence_bps: u64,
+    pub max_staleness_slots: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetOptionLimitsArgs {
+    pub min_moneyness_bps: u64,
+    pub max_moneyness_bps: u64,
+    pub strike_tick_size: u64,
+    pub min_time_to_expiry_seconds: u64,
+    pub max_tenor_seconds: u64,
 }
 
 #[account]
@@ -655,14 +2448,32 @@ pub struct OptionAccount {
     pub option_mint_nonce: u8,
     pub creator_option_token_account_nonce: u8,
     pub vault_nonce: u8,
+    pub settlement_vault_nonce: u8,
+    pub writer_mint_nonce: u8,
+    pub creator_writer_token_account_nonce: u8,
 
     pub underlying_count: u64,
     pub option_mint: Pubkey,
+    // Mints the tokenized claim on the collateral left after settlement.
+    pub writer_mint: Pubkey,
     pub underlying_mint: Pubkey,
+    // Underlying mint for calls, the state quote mint for puts.
+    pub collateral_mint: Pubkey,
+    // Mint paid into the settlement vault on physical exercise.
+    pub settlement_mint: Pubkey,
     pub creator: Pubkey,
+    pub kind: OptionKind,
+    pub settlement_mode: SettlementMode,
+    pub exercise_style: ExerciseStyle,
+    pub settlement_source: SettlementSource,
+    // Strike of the long leg for spreads.
     pub strike: u64,
+    pub short_strike: u64,
     pub expiry: u64,
     pub settlement_price: u64,
+    // When the settlement price was set, starts the dispute window.
+    pub settlement_ts: u64,
+    pub status: OptionStatus,
 
     pub profit_per_option: u64,
     pub remaining_collateral: u64,
@@ -672,9 +2483,66 @@ pub struct OptionAccount {
 #[derive(Default)]
 pub struct Underlying {
     pub underlying_nonce: u8,
+    // Zero padded ascii ticker.
+    pub symbol: [u8; MAX_SYMBOL_LENGTH],
     pub mint: Pubkey,
     pub oracle: Pubkey,
     pub count: u64,
+    // Widest oracle confidence interval, as a fraction of the price, that
+    // expire_option will settle on.
+    pub max_confidence_bps: u64,
+    // Oldest aggregate price, in slots, that expire_option will settle on.
+    pub max_staleness_slots: u64,
+    // Every feed expire_option reads, the first one is `oracle`.
+    pub oracles: [Pubkey; MAX_ORACLES],
+    pub oracle_types: [OracleType; MAX_ORACLES],
+    pub oracle_count: u8,
+    // Fresh feeds within `max_oracle_deviation_bps` of their median needed
+    // to settle.
+    pub oracle_quorum: u8,
+    pub max_oracle_deviation_bps: u64,
+    // Combined with the global flags on State.
+    pub pause_flags: PauseFlags,
+    // Limits on new options, zero leaves the limit unset. Moneyness is the
+    // strike as a fraction of the oracle price at creation.
+    pub min_moneyness_bps: u64,
+    pub max_moneyness_bps: u64,
+    pub strike_tick_size: u64,
+    pub min_time_to_expiry_seconds: u64,
+    pub max_tenor_seconds: u64,
+    pub expiry_calendar: ExpiryCalendar,
+}
+
+impl Underlying {
+    pub fn get_symbol(&self) -> &str {
+        let len = self
+            .symbol
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(MAX_SYMBOL_LENGTH);
+        std::str::from_utf8(&self.symbol[..len]).unwrap()
+    }
+}
+
+// Price pushed by a trusted authority, used as a fallback feed.
+#[account]
+#[derive(Default)]
+pub struct AdminPrice {
+    pub authority: Pubkey,
+    // In USDC_DECIMALS.
+    pub price: u64,
+    pub conf: u64,
+    pub pub_slot: u64,
+}
+
+#[account]
+#[derive(Default)]
+pub struct SettlementObservations {
+    pub settlement_observations_nonce: u8,
+    pub option_account: Pubkey,
+    pub last_observation_slot: u64,
+    // Oracle prices in USDC_DECIMALS, in the order they were recorded.
+    pub prices: Vec<u64>,
 }
 
 #[account]
@@ -684,7 +2552,315 @@ pub struct State {
     pub mint_auth_nonce: u8,
     pub vault_auth_nonce: u8,
     pub admin: Pubkey,
+    pub quote_mint: Pubkey,
     pub settlement_price_threshold_seconds: u32,
+    // Payouts stay locked for this long after settlement so the settlement
+    // authority or guardian can correct a bad price.
+    pub settlement_dispute_seconds: u32,
+    // Pauses the protocol and disputes settlements during an incident.
+    pub guardian: Pubkey,
+    // Set by propose_admin, becomes admin once it signs accept_admin.
+    pub pending_admin: Pubkey,
+    // Lists underlyings and sets their option limits and expiry calendars.
+    // Their oracles can only be changed through the admin timelock.
+    pub listing_authority: Pubkey,
+    // Overrides and corrects settlement prices.
+    pub settlement_authority: Pubkey,
+    // Delay between queueing and executing an AdminAction.
+    pub admin_action_delay_seconds: u32,
+    pub queued_action_count: u64,
+    pub pause_flags: PauseFlags,
+    // Protocol fees, paid into the collateral mint's treasury.
+    // Charged on top of the collateral written.
+    pub mint_fee_bps: u64,
+    // Skimmed from exercise payouts.
+    pub exercise_fee_bps: u64,
+    // Skimmed from the collateral returned to writers.
+    pub settlement_fee_bps: u64,
+    // How long after settlement holders and writers have to claim before
+    // close_option can sweep what's left to the treasury.
+    pub claim_period_seconds: u32,
+    // How long physical holders have to exercise once payouts unlock. Options
+    // left unexercised lapse and their collateral returns to the writers.
+    pub exercise_period_seconds: u32,
+}
+
+// Expiries new options may list on. With no schedule enabled any expiry is
+// allowed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct ExpiryCalendar {
+    // Seconds after midnight UTC that every listed expiry falls on.
+    pub time_of_day_seconds: u32,
+    pub daily: bool,
+    // Every Friday.
+    pub weekly: bool,
+    // The last Friday of each month.
+    pub monthly: bool,
+}
+
+impl ExpiryCalendar {
+    pub fn is_restricted(&self) -> bool {
+        self.daily || self.weekly || self.monthly
+    }
+
+    pub fn is_valid_expiry(&self, expiry: u64) -> bool {
+        if !self.is_restricted() {
+            return true;
+        }
+        if expiry % SECONDS_PER_DAY != self.time_of_day_seconds as u64 {
+            return false;
+        }
+
+        let day = expiry / SECONDS_PER_DAY;
+        self.daily
+            || (self.weekly && is_friday(day))
+            || (self.monthly && is_friday(day) && get_month(day) != get_month(day + 7))
+    }
+
+    // The next `count` expiries after `now`, for clients choosing a series.
+    // Empty when the calendar is unrestricted.
+    pub fn next_valid_expiries(&self, now: u64, count: usize) -> Vec<u64> {
+        let mut expiries = Vec::with_capacity(count);
+        if !self.is_restricted() {
+            return expiries;
+        }
+
+        let mut day = now / SECONDS_PER_DAY;
+        while expiries.len() < count {
+            let expiry = day * SECONDS_PER_DAY + self.time_of_day_seconds as u64;
+            if expiry > now && self.is_valid_expiry(expiry) {
+                expiries.push(expiry);
+            }
+            day += 1;
+        }
+        expiries
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PauseFlags {
+    // initialize_option and write_more_options.
+    pub mint: bool,
+    // burn_option, collect_remaining_collateral and close_option.
+    pub burn: bool,
+    // Oracle settlement. Overrides and disputes stay available so the
+    // settlement authority can still settle by hand.
+    pub settlement: bool,
+    // exercise_option and auto_exercise.
+    pub exercise: bool,
+}
+
+#[account]
+#[derive(Default)]
+pub struct QueuedAction {
+    pub queued_action_nonce: u8,
+    pub index: u64,
+    pub action: AdminAction,
+    // Earliest time the action can be executed.
+    pub eta: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OptionKind {
+    // Covered call, collateralized by the underlying mint.
+    Call,
+    // Cash-secured put, collateralized by the quote mint.
+    Put,
+    // Long call at `strike`, short call at a higher `short_strike`.
+    // Collateralized by the maximum payoff in the quote mint.
+    CallSpread,
+    // Long put at `strike`, short put at a lower `short_strike`.
+    // Collateralized by the maximum payoff in the quote mint.
+    PutSpread,
+}
+
+impl Default for OptionKind {
+    fn default() -> Self {
+        OptionKind::Call
+    }
+}
+
+impl OptionKind {
+    pub fn collateral_mint(&self, underlying_mint: Pubkey, quote_mint: Pubkey) -> Pubkey {
+        match self {
+            OptionKind::Call => underlying_mint,
+            OptionKind::Put | OptionKind::CallSpread | OptionKind::PutSpread => quote_mint,
+        }
+    }
+
+    pub fn settlement_mint(&self, underlying_mint: Pubkey, quote_mint: Pubkey) -> Pubkey {
+        match self {
+            OptionKind::Put => underlying_mint,
+            OptionKind::Call | OptionKind::CallSpread | OptionKind::PutSpread => quote_mint,
+        }
+    }
+
+    pub fn is_spread(&self) -> bool {
+        matches!(self, OptionKind::CallSpread | OptionKind::PutSpread)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            OptionKind::Call => "C",
+            OptionKind::Put => "P",
+            OptionKind::CallSpread => "CS",
+            OptionKind::PutSpread => "PS",
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum SettlementMode {
+    // Holders receive the intrinsic value out of the collateral.
+    Cash,
+    // Holders pay the strike (calls) or deliver the underlying (puts) and
+    // receive the full collateral lot.
+    Physical,
+}
+
+impl Default for SettlementMode {
+    fn default() -> Self {
+        SettlementMode::Cash
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum ExerciseStyle {
+    // Exercisable only after expiry, against the settlement price.
+    European,
+    // Also exercisable at any time before expiry, against the oracle price.
+    American,
+}
+
+impl Default for ExerciseStyle {
+    fn default() -> Self {
+        ExerciseStyle::European
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum SettlementSource {
+    // The aggregate oracle price at the time expire_option is called.
+    Spot,
+    // The oracle's time-weighted average price, which is harder to move
+    // within the settlement window.
+    Twap,
+    // Mean or median of the prices recorded by keepers over the settlement
+    // window, settled through expire_option_from_observations.
+    ObservationMean,
+    ObservationMedian,
+}
+
+impl Default for SettlementSource {
+    fn default() -> Self {
+        SettlementSource::Spot
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum AdminAction {
+    // Replaces the underlying's primary Pyth feed.
+    SetUnderlyingOracle {
+        underlying: Pubkey,
+        oracle: Pubkey,
+    },
+    // Adds a secondary feed used by the median settlement price.
+    AddOracle {
+        underlying: Pubkey,
+        oracle: Pubkey,
+        oracle_type: OracleType,
+    },
+    SetOracleQuorum {
+        underlying: Pubkey,
+        oracle_quorum: u8,
+        max_oracle_deviation_bps: u64,
+    },
+    SetOracleLimits {
+        underlying: Pubkey,
+        max_confidence_bps: u64,
+        max_staleness_slots: u64,
+    },
+    SetSettlementPriceThresholdSeconds {
+        seconds: u32,
+    },
+    // Also moves the dispute window and claim start of settled series.
+    SetSettlementDisputeSeconds {
+        seconds: u32,
+    },
+}
+
+impl AdminAction {
+    pub fn get_underlying(&self) -> Option<Pubkey> {
+        match *self {
+            AdminAction::SetUnderlyingOracle { underlying, .. }
+            | AdminAction::AddOracle { underlying, .. }
+            | AdminAction::SetOracleQuorum { underlying, .. }
+            | AdminAction::SetOracleLimits { underlying, .. } => Some(underlying),
+            AdminAction::SetSettlementPriceThresholdSeconds { .. }
+            | AdminAction::SetSettlementDisputeSeconds { .. } => None,
+        }
+    }
+}
+
+impl Default for AdminAction {
+    fn default() -> Self {
+        AdminAction::SetSettlementPriceThresholdSeconds { seconds: 0 }
+    }
+}
+
+// A zero settlement price is valid, so the lifecycle is tracked explicitly.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OptionStatus {
+    // Open for writing, burning and American exercise.
+    Active,
+    // Keepers have started recording settlement observations.
+    Settling,
+    // Settlement price set, payouts unlock after the dispute window.
+    Settled,
+    // Settlement price corrected by dispute_settlement.
+    Disputed,
+    // Every option and writer token has been redeemed.
+    Closed,
+}
+
+impl Default for OptionStatus {
+    fn default() -> Self {
+        OptionStatus::Active
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OracleType {
+    // pyth::pc::Price account.
+    Pyth,
+    // AdminPrice account owned by this program.
+    AdminPrice,
+}
+
+impl Default for OracleType {
+    fn default() -> Self {
+        OracleType::Pyth
+    }
+}
+
+impl SettlementSource {
+    pub fn uses_observations(&self) -> bool {
+        matches!(
+            self,
+            SettlementSource::ObservationMean | SettlementSource::ObservationMedian
+        )
+    }
+}
+
+impl<'info> WithdrawFromTreasury<'info> {
+    pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.treasury.to_account_info().clone(),
+            to: self.destination.to_account_info().clone(),
+            authority: self.state.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
 }
 
 impl<'info> InitializeOption<'info> {
@@ -697,14 +2873,120 @@ impl<'info> InitializeOption<'info> {
         CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
     }
 
+    pub fn into_mint_writer_to_context(&self) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
+        let cpi_accounts = MintTo {
+            mint: self.writer_mint.to_account_info().clone(),
+            to: self.user_writer_token_account.to_account_info().clone(),
+            authority: self.mint_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
     pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
-            from: self.underlying_token_account.to_account_info().clone(),
+            from: self.collateral_token_account.to_account_info().clone(),
             to: self.vault.to_account_info().clone(),
             authority: self.creator.to_account_info().clone(),
         };
         CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
     }
+
+    pub fn into_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.collateral_token_account.to_account_info().clone(),
+            to: self.treasury.to_account_info().clone(),
+            authority: self.creator.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    // The mint authority PDA also becomes the metadata's update authority.
+    // The uri points wallets back at the series and its underlying, which
+    // the name alone can't identify.
+    pub fn create_option_metadata(
+        &self,
+        name: String,
+        symbol: String,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let args = token_metadata::CreateMetadataAccountArgsV3 {
+            data: token_metadata::DataV2 {
+                name,
+                symbol,
+                uri: format!(
+                    "zeta-otc://option/{}?underlying_mint={}",
+                    self.option_account.key(),
+                    self.underlying_mint.key()
+                ),
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            is_mutable: false,
+            collection_details: None,
+        };
+        let ix = token_metadata::create_metadata_accounts_v3(
+            self.option_metadata.key(),
+            self.option_mint.key(),
+            self.mint_authority.key(),
+            self.creator.key(),
+            self.mint_authority.key(),
+            args,
+        );
+        invoke_signed(
+            &ix,
+            &[
+                self.option_metadata.to_account_info().clone(),
+                self.option_mint.to_account_info().clone(),
+                self.mint_authority.to_account_info().clone(),
+                self.creator.to_account_info().clone(),
+                self.mint_authority.to_account_info().clone(),
+                self.system_program.to_account_info().clone(),
+                self.metadata_program.to_account_info().clone(),
+            ],
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+}
+
+impl<'info> WriteMoreOptions<'info> {
+    pub fn into_mint_to_context(&self) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
+        let cpi_accounts = MintTo {
+            mint: self.option_mint.to_account_info().clone(),
+            to: self.user_option_token_account.to_account_info().clone(),
+            authority: self.mint_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_mint_writer_to_context(&self) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
+        let cpi_accounts = MintTo {
+            mint: self.writer_mint.to_account_info().clone(),
+            to: self.user_writer_token_account.to_account_info().clone(),
+            authority: self.mint_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.collateral_token_account.to_account_info().clone(),
+            to: self.vault.to_account_info().clone(),
+            authority: self.writer.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.collateral_token_account.to_account_info().clone(),
+            to: self.treasury.to_account_info().clone(),
+            authority: self.writer.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
 }
 
 impl<'info> BurnOption<'info> {
@@ -712,7 +2994,80 @@ impl<'info> BurnOption<'info> {
         let cpi_accounts = Burn {
             mint: self.option_mint.to_account_info().clone(),
             to: self.user_option_token_account.to_account_info().clone(),
-            authority: self.creator.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_burn_writer_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        let cpi_accounts = Burn {
+            mint: self.writer_mint.to_account_info().clone(),
+            to: self.user_writer_token_account.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info().clone(),
+            to: self.collateral_token_account.to_account_info().clone(),
+            authority: self.vault_authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
+impl<'info> AutoExercise<'info> {
+    pub fn into_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info().clone(),
+            to: self.treasury.to_account_info().clone(),
+            authority: self.vault_authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_transfer_context(
+        &self,
+        collateral_token_account: AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info().clone(),
+            to: collateral_token_account,
+            authority: self.vault_authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_burn_context(
+        &self,
+        user_option_token_account: AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        let cpi_accounts = Burn {
+            mint: self.option_mint.to_account_info().clone(),
+            to: user_option_token_account,
+            authority: self.mint_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
+impl<'info> CollectRemainingCollateral<'info> {
+    pub fn into_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info().clone(),
+            to: self.treasury.to_account_info().clone(),
+            authority: self.vault_authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_burn_writer_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        let cpi_accounts = Burn {
+            mint: self.writer_mint.to_account_info().clone(),
+            to: self.user_writer_token_account.to_account_info().clone(),
+            authority: self.writer.to_account_info().clone(),
         };
         CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
     }
@@ -720,45 +3075,94 @@ impl<'info> BurnOption<'info> {
     pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self.vault.to_account_info().clone(),
-            to: self.underlying_token_account.to_account_info().clone(),
+            to: self.collateral_token_account.to_account_info().clone(),
+            authority: self.vault_authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_settlement_transfer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.settlement_vault.to_account_info().clone(),
+            to: self.settlement_token_account.to_account_info().clone(),
             authority: self.vault_authority.to_account_info().clone(),
         };
         CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
     }
 }
 
-impl<'info> CollectRemainingCollateral<'info> {
-    pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+impl<'info> CloseOption<'info> {
+    pub fn into_sweep_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self.vault.to_account_info().clone(),
-            to: self.underlying_token_account.to_account_info().clone(),
-            authority: self.vault_authority.to_account_info().clone(),
+            to: self.treasury.to_account_info().clone(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_settlement_sweep_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.settlement_vault.to_account_info().clone(),
+            to: self.settlement_treasury.to_account_info().clone(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_close_vault_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.vault.to_account_info().clone(),
+            destination: self.creator.clone(),
+            authority: self.vault_authority.clone(),
         };
         CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
     }
 
-    pub fn into_close_user_token_account(
+    pub fn into_close_settlement_vault_context(
         &self,
     ) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
         let cpi_accounts = CloseAccount {
-            account: self.user_option_token_account.to_account_info().clone(),
-            destination: self.creator.to_account_info().clone(),
-            authority: self.creator.to_account_info().clone(),
+            account: self.settlement_vault.to_account_info().clone(),
+            destination: self.creator.clone(),
+            authority: self.vault_authority.clone(),
         };
         CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
     }
 }
 
 impl<'info> ExerciseOption<'info> {
+    pub fn into_fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info().clone(),
+            to: self.treasury.to_account_info().clone(),
+            authority: self.vault_authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
     pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self.vault.to_account_info().clone(),
-            to: self.underlying_token_account.to_account_info().clone(),
+            to: self.collateral_token_account.to_account_info().clone(),
             authority: self.vault_authority.to_account_info().clone(),
         };
         CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
     }
 
+    pub fn into_settlement_transfer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.settlement_token_account.to_account_info().clone(),
+            to: self.settlement_vault.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
     pub fn into_burn_context(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
         let cpi_accounts = Burn {
             mint: self.option_mint.to_account_info().clone(),
@@ -769,14 +3173,233 @@ impl<'info> ExerciseOption<'info> {
     }
 }
 
-pub fn get_oracle_price(oracle: &AccountInfo) -> u128 {
-    let oracle_price = pc::Price::load(&oracle).unwrap();
+// (price, confidence) of the aggregate in USDC_DECIMALS.
+pub fn get_oracle_price(oracle: &AccountInfo) -> Result<(u64, u64)> {
+    let oracle_price = pc::Price::load(&oracle)?;
     msg!("oracle price {}", oracle_price.agg.price);
-    (oracle_price.agg.price as u128)
-        .checked_mul(10u128.pow(USDC_DECIMALS))
-        .unwrap()
-        .checked_div(10u128.pow((-oracle_price.expo) as u32))
-        .unwrap() as u128
+    normalize_oracle_price(
+        oracle_price.agg.price,
+        oracle_price.agg.conf,
+        oracle_price.expo,
+    )
+}
+
+pub fn get_oracle_settlement_price(oracle: &AccountInfo, source: SettlementSource) -> Result<u64> {
+    match source {
+        SettlementSource::Twap => {
+            let oracle_price = pc::Price::load(&oracle)?;
+            msg!("oracle twap {}", oracle_price.twap);
+            let (twap, _) = normalize_oracle_price(oracle_price.twap, 0, oracle_price.expo)?;
+            Ok(twap)
+        }
+        _ => Ok(get_oracle_price(oracle)?.0),
+    }
+}
+
+pub fn get_mean_price(prices: &[u64]) -> u64 {
+    let sum: u128 = prices.iter().map(|price| *price as u128).sum();
+    sum.checked_div(prices.len() as u128).unwrap() as u64
+}
+
+pub fn get_median_price(prices: &[u64]) -> u64 {
+    let mut sorted = prices.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        ((sorted[mid - 1] as u128 + sorted[mid] as u128) / 2) as u64
+    } else {
+        sorted[mid]
+    }
+}
+
+// Converts a raw oracle price and confidence with exponent `expo` to
+// USDC_DECIMALS, rounding down.
+pub fn normalize_oracle_price(price: i64, conf: u64, expo: i32) -> Result<(u64, u64)> {
+    if price <= 0 {
+        return Err(ErrorCode::NonPositiveOraclePrice.into());
+    }
+    let price = scale_to_quote_decimals(price as u128, expo)?;
+    if price == 0 {
+        return Err(ErrorCode::OraclePriceOutOfRange.into());
+    }
+    let conf = scale_to_quote_decimals(conf as u128, expo)?;
+    Ok((price, conf))
+}
+
+pub fn scale_to_quote_decimals(value: u128, expo: i32) -> Result<u64> {
+    let shift = expo as i64 + USDC_DECIMALS as i64;
+    let scaled = if shift >= 0 {
+        10u128
+            .checked_pow(shift as u32)
+            .and_then(|scale| value.checked_mul(scale))
+    } else {
+        // Anything past 10^38 scales every u128 down to zero.
+        Some(
+            10u128
+                .checked_pow((-shift) as u32)
+                .map_or(0, |scale| value / scale),
+        )
+    };
+    match scaled {
+        Some(scaled) if scaled <= u64::MAX as u128 => Ok(scaled as u64),
+        _ => Err(ErrorCode::OraclePriceOutOfRange.into()),
+    }
+}
+
+// Settlement requires a trading feed that published recently.
+pub fn check_oracle_is_live(
+    oracle: &AccountInfo,
+    max_staleness_slots: u64,
+    current_slot: u64,
+) -> ProgramResult {
+    let oracle_price = pc::Price::load(&oracle)?;
+    if !matches!(oracle_price.agg.status, pc::PriceStatus::Trading) {
+        return Err(ErrorCode::OracleNotTrading.into());
+    }
+    check_oracle_staleness(oracle_price.agg.pub_slot, max_staleness_slots, current_slot)
+}
+
+pub fn check_oracle_staleness(
+    pub_slot: u64,
+    max_staleness_slots: u64,
+    current_slot: u64,
+) -> ProgramResult {
+    let staleness = current_slot.saturating_sub(pub_slot);
+    if staleness > max_staleness_slots {
+        msg!(
+            "Oracle price is {} slots old > max {} slots",
+            staleness,
+            max_staleness_slots
+        );
+        return Err(ErrorCode::OracleStale.into());
+    }
+    Ok(())
+}
+
+// (price, confidence in bps) of a single feed, erroring if it is not fresh
+// enough to settle on.
+pub fn get_fresh_oracle_price(
+    oracle: &AccountInfo,
+    oracle_type: OracleType,
+    source: SettlementSource,
+    underlying: &Underlying,
+    current_slot: u64,
+) -> Result<(u64, u64)> {
+    let (price, confidence_bps) = match oracle_type {
+        OracleType::Pyth => {
+            check_oracle_is_live(oracle, underlying.max_staleness_slots, current_slot)?;
+            (
+                get_oracle_settlement_price(oracle, source)?,
+                get_oracle_confidence_bps(oracle)?,
+            )
+        }
+        OracleType::AdminPrice => {
+            let admin_price: Account<AdminPrice> = Account::try_from(oracle)?;
+            check_oracle_staleness(
+                admin_price.pub_slot,
+                underlying.max_staleness_slots,
+                current_slot,
+            )?;
+            if admin_price.price == 0 {
+                return Err(ErrorCode::OracleNotTrading.into());
+            }
+            (
+                admin_price.price,
+                get_ratio_bps(admin_price.conf as u128, admin_price.price as u128),
+            )
+        }
+    };
+
+    // Uncertain prints have to be settled manually by the admin.
+    if confidence_bps > underlying.max_confidence_bps {
+        msg!(
+            "Oracle confidence {} bps > max {} bps",
+            confidence_bps,
+            underlying.max_confidence_bps
+        );
+        return Err(ErrorCode::OracleConfidenceTooWide.into());
+    }
+    Ok((price, confidence_bps))
+}
+
+// (median, widest confidence in bps) of the fresh feeds. Feeds that can't be
+// read count against the quorum, as do fresh feeds too far from the median.
+pub fn get_median_oracle_price(
+    underlying: &Underlying,
+    oracles: &[AccountInfo],
+    source: SettlementSource,
+    current_slot: u64,
+) -> Result<(u64, u64)> {
+    if oracles.len() != underlying.oracle_count as usize {
+        return Err(ErrorCode::InvalidOracle.into());
+    }
+
+    let mut prices = Vec::with_capacity(oracles.len());
+    let mut confidence_bps = 0;
+    let mut first_error = None;
+    for (i, oracle) in oracles.iter().enumerate() {
+        if oracle.key() != underlying.oracles[i] {
+            return Err(ErrorCode::InvalidOracle.into());
+        }
+        match get_fresh_oracle_price(
+            oracle,
+            underlying.oracle_types[i],
+            source,
+            underlying,
+            current_slot,
+        ) {
+            Ok((price, price_confidence_bps)) => {
+                prices.push(price);
+                confidence_bps = std::cmp::max(confidence_bps, price_confidence_bps);
+            }
+            Err(err) => {
+                msg!("Skipping oracle {}", oracle.key());
+                if first_error.is_none() {
+                    first_error = Some(err);
+                }
+            }
+        }
+    }
+
+    if prices.len() < underlying.oracle_quorum as usize {
+        return Err(first_error.unwrap_or_else(|| ErrorCode::OracleQuorumNotMet.into()));
+    }
+
+    let median = get_median_price(&prices);
+    let agreeing = prices
+        .iter()
+        .filter(|price| {
+            let deviation = (**price as i128 - median as i128).abs() as u128;
+            get_ratio_bps(deviation, median as u128) <= underlying.max_oracle_deviation_bps
+        })
+        .count();
+    if agreeing < underlying.oracle_quorum as usize {
+        msg!(
+            "{} oracles agree < quorum {}",
+            agreeing,
+            underlying.oracle_quorum
+        );
+        return Err(ErrorCode::OracleQuorumNotMet.into());
+    }
+    Ok((median, confidence_bps))
+}
+
+// Oracle confidence interval as a fraction of the aggregate price, in bps.
+pub fn get_oracle_confidence_bps(oracle: &AccountInfo) -> Result<u64> {
+    let (price, conf) = get_oracle_price(oracle)?;
+    Ok(get_ratio_bps(conf as u128, price as u128))
+}
+
+// `amount` as a fraction of `price`, in bps. Saturates so an oversized
+// amount fails the limit it's checked against instead of wrapping under it.
+pub fn get_ratio_bps(amount: u128, price: u128) -> u64 {
+    if price == 0 {
+        return u64::MAX;
+    }
+    match amount.checked_mul(BPS_DENOMINATOR as u128) {
+        Some(scaled) => std::cmp::min(scaled / price, u64::MAX as u128) as u64,
+        None => u64::MAX,
+    }
 }
 
 #[macro_export]
@@ -793,6 +3416,14 @@ macro_rules! vault_authority {
     };
 }
 
+// State signs for the treasury accounts it owns.
+#[macro_export]
+macro_rules! state_authority {
+    (bump = $bump:expr) => {
+        &[STATE_SEED.as_bytes().as_ref(), &[$bump]]
+    };
+}
+
 pub fn get_token_amount_per_option(mint_account: &Mint) -> u64 {
     10u64
         .pow(mint_account.decimals.into())
@@ -800,39 +3431,456 @@ pub fn get_token_amount_per_option(mint_account: &Mint) -> u64 {
         .unwrap()
 }
 
+// Quote tokens per option for a USDC_DECIMALS denominated price, i.e. the
+// value of one option's contract size at `price`.
+pub fn get_quote_amount_per_option(price: u64, quote_mint: &Mint) -> u64 {
+    (price as u128)
+        .checked_mul(10u128.pow(quote_mint.decimals.into()))
+        .unwrap()
+        .checked_div(10u128.pow(USDC_DECIMALS + OPTION_MINT_DECIMALS as u32))
+        .unwrap() as u64
+}
+
+pub fn get_collateral_amount_per_option(
+    option_account: &OptionAccount,
+    collateral_mint: &Mint,
+) -> u64 {
+    match option_account.kind {
+        OptionKind::Call => get_token_amount_per_option(collateral_mint),
+        OptionKind::Put => get_quote_amount_per_option(option_account.strike, collateral_mint),
+        OptionKind::CallSpread | OptionKind::PutSpread => {
+            get_quote_amount_per_option(get_spread_width(option_account), collateral_mint)
+        }
+    }
+}
+
+// Distance between the two strikes of a spread, i.e. its maximum payoff.
+pub fn get_spread_width(option_account: &OptionAccount) -> u64 {
+    if option_account.strike > option_account.short_strike {
+        option_account.strike - option_account.short_strike
+    } else {
+        option_account.short_strike - option_account.strike
+    }
+}
+
+// What a physically settled holder pays per option on exercise: the strike in
+// quote tokens for calls, one lot of the underlying for puts.
+pub fn get_exercise_payment_per_option(
+    option_account: &OptionAccount,
+    settlement_mint: &Mint,
+) -> u64 {
+    match option_account.kind {
+        OptionKind::Call => get_quote_amount_per_option(option_account.strike, settlement_mint),
+        OptionKind::Put => get_token_amount_per_option(settlement_mint),
+        // Spreads are always cash settled.
+        OptionKind::CallSpread | OptionKind::PutSpread => 0,
+    }
+}
+
+// Payout per option at `price`, in collateral tokens.
+pub fn get_profit_per_option(option_account: &OptionAccount, price: u64, mint: &Mint) -> u64 {
+    let in_the_money = match option_account.kind {
+        OptionKind::Call | OptionKind::CallSpread => price > option_account.strike,
+        OptionKind::Put | OptionKind::PutSpread => price < option_account.strike,
+    };
+
+    if !in_the_money {
+        // The option is worthless at this price.
+        0
+    } else if option_account.settlement_mode == SettlementMode::Physical {
+        // Holders take delivery of the whole collateral lot on exercise.
+        get_collateral_amount_per_option(option_account, mint)
+    } else {
+        match option_account.kind {
+            OptionKind::Call => {
+                let itm_amount = price.checked_sub(option_account.strike).unwrap();
+
+                // 100_000
+                let token_amount_per_option = get_token_amount_per_option(&mint);
+
+                // (Oracle spot - strike) / oracle_spot) * units of underlying per option
+                token_amount_per_option
+                    .checked_mul(itm_amount)
+                    .unwrap()
+                    .checked_div(price)
+                    .unwrap()
+            }
+            OptionKind::Put => {
+                let itm_amount = option_account.strike.checked_sub(price).unwrap();
+
+                // (Strike - oracle spot) * contract size, paid in quote tokens.
+                get_quote_amount_per_option(itm_amount, &mint)
+            }
+            OptionKind::CallSpread => {
+                // Gains above the short strike belong to the writer.
+                let itm_amount = price
+                    .min(option_account.short_strike)
+                    .checked_sub(option_account.strike)
+                    .unwrap();
+                get_quote_amount_per_option(itm_amount, &mint)
+            }
+            OptionKind::PutSpread => {
+                // Gains below the short strike belong to the writer.
+                let itm_amount = option_account
+                    .strike
+                    .checked_sub(price.max(option_account.short_strike))
+                    .unwrap();
+                get_quote_amount_per_option(itm_amount, &mint)
+            }
+        }
+    }
+}
+
+pub fn check_option_limits(
+    underlying: &Underlying,
+    oracle: &AccountInfo,
+    args: &InitializeOptionArgs,
+    now: i64,
+) -> ProgramResult {
+    let time_to_expiry = args.expiry.checked_sub(now as u64).unwrap();
+    if time_to_expiry < underlying.min_time_to_expiry_seconds {
+        return Err(ErrorCode::ExpiryTooSoon.into());
+    }
+    if underlying.max_tenor_seconds != 0 && time_to_expiry > underlying.max_tenor_seconds {
+        return Err(ErrorCode::ExpiryTooFar.into());
+    }
+    if !underlying.expiry_calendar.is_valid_expiry(args.expiry) {
+        return Err(ErrorCode::ExpiryNotOnCalendar.into());
+    }
+
+    let mut strikes = vec![args.strike];
+    if args.kind.is_spread() {
+        strikes.push(args.short_strike);
+    }
+
+    let check_moneyness = underlying.min_moneyness_bps != 0 || underlying.max_moneyness_bps != 0;
+    let oracle_price = if check_moneyness {
+        get_oracle_price(oracle)?.0
+    } else {
+        0
+    };
+
+    for strike in strikes {
+        if strike == 0 {
+            return Err(ErrorCode::StrikeMustBeNonZero.into());
+        }
+
+        if underlying.strike_tick_size != 0 && strike % underlying.strike_tick_size != 0 {
+            return Err(ErrorCode::StrikeNotOnTickSize.into());
+        }
+
+        if check_moneyness {
+            let moneyness_bps = get_ratio_bps(strike.into(), oracle_price.into());
+            if moneyness_bps < underlying.min_moneyness_bps {
+                msg!("Strike {} is {} bps of spot", strike, moneyness_bps);
+                return Err(ErrorCode::StrikeBelowMinMoneyness.into());
+            }
+            if underlying.max_moneyness_bps != 0 && moneyness_bps > underlying.max_moneyness_bps {
+                msg!("Strike {} is {} bps of spot", strike, moneyness_bps);
+                return Err(ErrorCode::StrikeAboveMaxMoneyness.into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Days since the unix epoch, which was a Thursday.
+pub fn is_friday(day: u64) -> bool {
+    (day + 3) % 7 == 4
+}
+
+pub fn get_month(day: u64) -> (u64, u64) {
+    let (year, month, _) = get_date(day);
+    (year, month)
+}
+
+// (year, month, day of month) of days since the unix epoch, from Howard
+// Hinnant's civil_from_days.
+pub fn get_date(day: u64) -> (u64, u64, u64) {
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day_of_month)
+}
+
+// Strike in quote units with trailing zeros dropped, e.g. 150 or 150.5.
+pub fn format_strike(strike: u64) -> String {
+    let scale = 10u64.pow(USDC_DECIMALS);
+    let fraction = strike % scale;
+    if fraction == 0 {
+        return (strike / scale).to_string();
+    }
+    let fraction = format!("{:0width$}", fraction, width = USDC_DECIMALS as usize);
+    format!("{}.{}", strike / scale, fraction.trim_end_matches('0'))
+}
+
+// e.g. SOL-20261030-150-C, or SOL-20261030-150/170-CS for spreads.
+pub fn get_option_name(symbol: &str, args: &InitializeOptionArgs) -> String {
+    let (year, month, day) = get_date(args.expiry / SECONDS_PER_DAY);
+    let mut strikes = format_strike(args.strike);
+    if args.kind.is_spread() {
+        strikes = format!("{}/{}", strikes, format_strike(args.short_strike));
+    }
+    format!(
+        "{}-{:04}{:02}{:02}-{}-{}",
+        symbol,
+        year,
+        month,
+        day,
+        strikes,
+        args.kind.code()
+    )
+}
+
+pub fn get_fee(amount: u64, fee_bps: u64) -> u64 {
+    amount
+        .checked_mul(fee_bps)
+        .unwrap()
+        .checked_div(BPS_DENOMINATOR)
+        .unwrap()
+}
+
+// Collateral plus the fee charged on top of it.
+pub fn get_amount_with_fee(amount: u64, fee_bps: u64) -> u64 {
+    amount.checked_add(get_fee(amount, fee_bps)).unwrap()
+}
+
+pub fn get_pause_flags(state: &State, underlying: &Underlying) -> PauseFlags {
+    PauseFlags {
+        mint: state.pause_flags.mint || underlying.pause_flags.mint,
+        burn: state.pause_flags.burn || underlying.pause_flags.burn,
+        settlement: state.pause_flags.settlement || underlying.pause_flags.settlement,
+        exercise: state.pause_flags.exercise || underlying.pause_flags.exercise,
+    }
+}
+
+pub fn apply_oracle_action(underlying: &mut Underlying, action: AdminAction) -> ProgramResult {
+    match action {
+        AdminAction::SetUnderlyingOracle { oracle, .. } => {
+            // A feed counted twice would skew the median.
+            let count = underlying.oracle_count as usize;
+            if count > 1 && underlying.oracles[1..count].contains(&oracle) {
+                return Err(ErrorCode::OracleAlreadyAdded.into());
+            }
+            underlying.oracle = oracle;
+            underlying.oracles[0] = oracle;
+            underlying.oracle_types[0] = OracleType::Pyth;
+        }
+        AdminAction::AddOracle {
+            oracle,
+            oracle_type,
+            ..
+        } => {
+            let index = underlying.oracle_count as usize;
+            if index >= MAX_ORACLES {
+                return Err(ErrorCode::TooManyOracles.into());
+            }
+            if underlying.oracles[..index].contains(&oracle) {
+                return Err(ErrorCode::OracleAlreadyAdded.into());
+            }
+            underlying.oracles[index] = oracle;
+            underlying.oracle_types[index] = oracle_type;
+            underlying.oracle_count = underlying.oracle_count.checked_add(1).unwrap();
+        }
+        AdminAction::SetOracleQuorum {
+            oracle_quorum,
+            max_oracle_deviation_bps,
+            ..
+        } => {
+            if oracle_quorum == 0 || oracle_quorum > underlying.oracle_count {
+                return Err(ErrorCode::InvalidOracleQuorum.into());
+            }
+            underlying.oracle_quorum = oracle_quorum;
+            underlying.max_oracle_deviation_bps = max_oracle_deviation_bps;
+        }
+        AdminAction::SetOracleLimits {
+            max_confidence_bps,
+            max_staleness_slots,
+            ..
+        } => {
+            underlying.max_confidence_bps = max_confidence_bps;
+            underlying.max_staleness_slots = max_staleness_slots;
+        }
+        AdminAction::SetSettlementPriceThresholdSeconds { .. }
+        | AdminAction::SetSettlementDisputeSeconds { .. } => {}
+    }
+    Ok(())
+}
+
+pub fn check_option_is_settled(option_account: &OptionAccount) -> ProgramResult {
+    match option_account.status {
+        OptionStatus::Settled | OptionStatus::Disputed => Ok(()),
+        OptionStatus::Active | OptionStatus::Settling => {
+            Err(ErrorCode::SettlementPriceNotSet.into())
+        }
+        OptionStatus::Closed => Err(ErrorCode::OptionIsClosed.into()),
+    }
+}
+
+pub fn get_dispute_end(option_account: &OptionAccount, state: &State) -> i64 {
+    option_account
+        .settlement_ts
+        .checked_add(state.settlement_dispute_seconds.into())
+        .unwrap() as i64
+}
+
+// Claims open at expiry once the dispute window has passed.
+pub fn get_claim_start(option_account: &OptionAccount, state: &State) -> i64 {
+    std::cmp::max(
+        option_account.expiry as i64,
+        get_dispute_end(option_account, state),
+    )
+}
+
+pub fn get_exercise_deadline(option_account: &OptionAccount, state: &State) -> i64 {
+    get_claim_start(option_account, state)
+        .checked_add(state.exercise_period_seconds.into())
+        .unwrap()
+}
+
+pub fn get_writer_share(pool: u64, writer_tokens: u64, writer_supply: u64) -> u64 {
+    if writer_supply == 0 {
+        return 0;
+    }
+    (pool as u128)
+        .checked_mul(writer_tokens as u128)
+        .unwrap()
+        .checked_div(writer_supply as u128)
+        .unwrap() as u64
+}
+
 pub fn set_profit_and_remaining_collateral(
     option_account: &mut OptionAccount,
     vault_amount: u64,
     mint: &Mint,
     option_supply: u64,
 ) -> Result<()> {
-    assert!(option_account.settlement_price != 0);
-    // If the option has expired worthless
-    if option_account.settlement_price <= option_account.strike {
-        option_account.profit_per_option = 0;
-        option_account.remaining_collateral = vault_amount;
-    } else {
-        let itm_amount = option_account
-            .settlement_price
-            .checked_sub(option_account.strike)
-            .unwrap();
+    let profit_per_option =
+        get_profit_per_option(option_account, option_account.settlement_price, mint);
 
-        // 100_000
-        let token_amount_per_option = get_token_amount_per_option(&mint);
+    let total_profit = profit_per_option.checked_mul(option_supply).unwrap();
+    option_account.profit_per_option = profit_per_option;
+    option_account.remaining_collateral = vault_amount.checked_sub(total_profit).unwrap();
 
-        // (Oracle spot - strike) / oracle_spot) * units of underlying per option
-        let profit_per_option = token_amount_per_option
-            .checked_mul(itm_amount)
-            .unwrap()
-            .checked_div(option_account.settlement_price)
-            .unwrap();
+    Ok(())
+}
 
-        let total_profit = profit_per_option.checked_mul(option_supply).unwrap();
-        option_account.profit_per_option = profit_per_option;
-        option_account.remaining_collateral = vault_amount.checked_sub(total_profit).unwrap();
-    }
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum SettlementMethod {
+    // expire_option, on the underlying's oracles.
+    Oracle,
+    // expire_option_from_observations.
+    Observations,
+    // expire_option_override.
+    Override,
+    // dispute_settlement.
+    Dispute,
+}
 
-    Ok(())
+#[event]
+pub struct StateInitialized {
+    pub state: Pubkey,
+    pub admin: Pubkey,
+    pub quote_mint: Pubkey,
+}
+
+#[event]
+pub struct UnderlyingInitialized {
+    pub underlying: Pubkey,
+    pub mint: Pubkey,
+    pub oracle: Pubkey,
+}
+
+// Empty when the underlying's calendar is unrestricted.
+#[event]
+pub struct ValidExpiries {
+    pub underlying: Pubkey,
+    pub expiries: Vec<u64>,
+}
+
+#[event]
+pub struct OptionInitialized {
+    pub option_account: Pubkey,
+    pub underlying: Pubkey,
+    pub creator: Pubkey,
+    pub kind: OptionKind,
+    pub settlement_mode: SettlementMode,
+    pub exercise_style: ExerciseStyle,
+    pub settlement_source: SettlementSource,
+    pub strike: u64,
+    pub short_strike: u64,
+    pub expiry: u64,
+    pub collateral_amount: u64,
+    pub option_amount: u64,
+    pub mint_fee: u64,
+}
+
+#[event]
+pub struct OptionsWritten {
+    pub option_account: Pubkey,
+    pub writer: Pubkey,
+    pub collateral_amount: u64,
+    pub option_amount: u64,
+    pub mint_fee: u64,
+}
+
+#[event]
+pub struct OptionsBurned {
+    pub option_account: Pubkey,
+    pub authority: Pubkey,
+    pub option_amount: u64,
+    pub collateral_amount: u64,
+}
+
+#[event]
+pub struct OptionSettled {
+    pub option_account: Pubkey,
+    pub method: SettlementMethod,
+    pub settlement_source: SettlementSource,
+    pub settlement_price: u64,
+    // Widest confidence among the feeds settled on, zero when not settled on
+    // the oracles.
+    pub confidence_bps: u64,
+    pub profit_per_option: u64,
+}
+
+#[event]
+pub struct OptionExercised {
+    pub option_account: Pubkey,
+    pub holder: Pubkey,
+    pub option_amount: u64,
+    // Net of the exercise fee.
+    pub payout: u64,
+    pub exercise_fee: u64,
+    pub early: bool,
+}
+
+#[event]
+pub struct CollateralCollected {
+    pub option_account: Pubkey,
+    pub writer: Pubkey,
+    pub writer_tokens: u64,
+    // Net of the settlement fee.
+    pub collateral_amount: u64,
+    pub settlement_amount: u64,
+    pub settlement_fee: u64,
+}
+
+#[event]
+pub struct OptionClosed {
+    pub option_account: Pubkey,
+    pub creator: Pubkey,
+    // Unclaimed balances moved to the treasury.
+    pub swept_collateral: u64,
+    pub swept_settlement: u64,
 }
 
 #[error]
@@ -877,4 +3925,332 @@ pub enum ErrorCode {
     OwnerMismatch,
     #[msg("Option mint mismatch")]
     OptionMintMismatch,
+    #[msg("Invalid collateral mint")]
+    InvalidCollateralMint,
+    #[msg("Invalid strike")]
+    InvalidStrike,
+    #[msg("Invalid settlement mint")]
+    InvalidSettlementMint,
+    #[msg("Physical options not fully exercised")]
+    PhysicalOptionsNotFullyExercised,
+    #[msg("Option is out of the money")]
+    OptionIsOutOfTheMoney,
+    #[msg("Invalid spread strikes")]
+    InvalidSpreadStrikes,
+    #[msg("Spreads must be cash settled")]
+    SpreadsMustBeCashSettled,
+    #[msg("Cannot write options after expiry")]
+    CannotWriteOptionsAfterExpiry,
+    #[msg("Cannot write options after settlement price is set")]
+    CannotWriteOptionsAfterSettlementPriceIsSet,
+    #[msg("Insufficient writer tokens")]
+    InsufficientWriterTokens,
+    #[msg("Auto exercise requires cash settlement")]
+    AutoExerciseRequiresCashSettlement,
+    #[msg("Invalid auto exercise accounts")]
+    InvalidAutoExerciseAccounts,
+    #[msg("Holder has not delegated options")]
+    HolderHasNotDelegatedOptions,
+    #[msg("Oracle confidence too wide, use expire option override")]
+    OracleConfidenceTooWide,
+    #[msg("Oracle is not trading")]
+    OracleNotTrading,
+    #[msg("Oracle price is stale")]
+    OracleStale,
+    #[msg("Settlement source requires observations")]
+    SettlementSourceRequiresObservations,
+    #[msg("Settlement source does not use observations")]
+    SettlementSourceDoesNotUseObservations,
+    #[msg("Too many settlement observations")]
+    TooManySettlementObservations,
+    #[msg("Settlement observation already recorded this slot")]
+    SettlementObservationAlreadyRecorded,
+    #[msg("No settlement observations")]
+    NoSettlementObservations,
+    #[msg("Too many oracles")]
+    TooManyOracles,
+    #[msg("Oracle already added")]
+    OracleAlreadyAdded,
+    #[msg("Invalid oracle quorum")]
+    InvalidOracleQuorum,
+    #[msg("Oracle quorum not met")]
+    OracleQuorumNotMet,
+    #[msg("Unauthorized price authority")]
+    UnauthorizedPriceAuthority,
+    #[msg("Settlement dispute window active")]
+    SettlementDisputeWindowActive,
+    #[msg("Settlement dispute window closed")]
+    SettlementDisputeWindowClosed,
+    #[msg("Unauthorized settlement disputer")]
+    UnauthorizedSettlementDisputer,
+    #[msg("Option is settling")]
+    OptionIsSettling,
+    #[msg("Option is closed")]
+    OptionIsClosed,
+    #[msg("Unauthorized pending admin")]
+    UnauthorizedPendingAdmin,
+    #[msg("Unauthorized listing authority")]
+    UnauthorizedListingAuthority,
+    #[msg("Unauthorized settlement authority")]
+    UnauthorizedSettlementAuthority,
+    #[msg("Admin action not ready")]
+    AdminActionNotReady,
+    #[msg("Invalid admin action accounts")]
+    InvalidAdminActionAccounts,
+    #[msg("Unauthorized guardian")]
+    UnauthorizedGuardian,
+    #[msg("Minting paused")]
+    MintingPaused,
+    #[msg("Burning paused")]
+    BurningPaused,
+    #[msg("Settlement paused")]
+    SettlementPaused,
+    #[msg("Exercise paused")]
+    ExercisePaused,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
+    #[msg("Invalid fee rate")]
+    InvalidFeeRate,
+    #[msg("Invalid option limits")]
+    InvalidOptionLimits,
+    #[msg("Strike must be non zero")]
+    StrikeMustBeNonZero,
+    #[msg("Strike not on tick size")]
+    StrikeNotOnTickSize,
+    #[msg("Strike below min moneyness")]
+    StrikeBelowMinMoneyness,
+    #[msg("Strike above max moneyness")]
+    StrikeAboveMaxMoneyness,
+    #[msg("Expiry too soon")]
+    ExpiryTooSoon,
+    #[msg("Expiry too far")]
+    ExpiryTooFar,
+    #[msg("Invalid expiry calendar")]
+    InvalidExpiryCalendar,
+    #[msg("Expiry not on calendar")]
+    ExpiryNotOnCalendar,
+    #[msg("Claim period active")]
+    ClaimPeriodActive,
+    #[msg("Invalid option creator")]
+    InvalidOptionCreator,
+    #[msg("Invalid underlying symbol")]
+    InvalidUnderlyingSymbol,
+    #[msg("Oracle price must be positive")]
+    NonPositiveOraclePrice,
+    #[msg("Oracle price out of range")]
+    OraclePriceOutOfRange,
+    #[msg("Settlement observation window still open")]
+    SettlementObservationWindowOpen,
+    #[msg("Invalid claim period")]
+    InvalidClaimPeriod,
+    #[msg("Option name too long")]
+    OptionNameTooLong,
+    #[msg("Invalid exercise period")]
+    InvalidExercisePeriod,
+    #[msg("Exercise period over")]
+    ExercisePeriodOver,
+    #[msg("Too many expiries requested")]
+    TooManyExpiriesRequested,
+    #[msg("Series has early exercises")]
+    SeriesHasEarlyExercises,
+    #[msg("Invalid settlement observations account")]
+    InvalidSettlementObservationsAccount,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPIRY_TIME_OF_DAY: u32 = 8 * 60 * 60;
+
+    // Days since the unix epoch.
+    const THURSDAY_2026_10_01: u64 = 20_727;
+    const THURSDAY_2026_10_29: u64 = 20_755;
+    const FRIDAY_2026_10_23: u64 = 20_749;
+    const FRIDAY_2026_10_30: u64 = 20_756;
+    const FRIDAY_2026_11_27: u64 = 20_784;
+    const FRIDAY_2026_12_25: u64 = 20_812;
+    const FRIDAY_2027_01_29: u64 = 20_847;
+
+    fn expiry_on(day: u64) -> u64 {
+        day * SECONDS_PER_DAY + EXPIRY_TIME_OF_DAY as u64
+    }
+
+    #[test]
+    fn test_is_friday() {
+        assert!(!is_friday(0));
+        assert!(is_friday(1));
+        assert!(is_friday(10_956));
+        assert!(!is_friday(THURSDAY_2026_10_29));
+        assert!(is_friday(FRIDAY_2026_10_30));
+    }
+
+    #[test]
+    fn test_get_date() {
+        assert_eq!(get_date(0), (1970, 1, 1));
+        assert_eq!(get_date(10_956), (1999, 12, 31));
+        assert_eq!(get_date(11_017), (2000, 3, 1));
+        assert_eq!(get_date(19_782), (2024, 2, 29));
+        assert_eq!(get_date(FRIDAY_2026_12_25), (2026, 12, 25));
+        assert_eq!(get_date(FRIDAY_2026_12_25 + 7), (2027, 1, 1));
+    }
+
+    #[test]
+    fn test_get_month() {
+        assert_eq!(get_month(FRIDAY_2026_10_30), (2026, 10));
+        assert_eq!(get_month(FRIDAY_2026_10_30 + 2), (2026, 11));
+        assert_eq!(get_month(FRIDAY_2026_12_25 + 7), (2027, 1));
+    }
+
+    #[test]
+    fn test_daily_expiries() {
+        let calendar = ExpiryCalendar {
+            time_of_day_seconds: EXPIRY_TIME_OF_DAY,
+            daily: true,
+            ..Default::default()
+        };
+        // Today's expiry has passed, so the first is tomorrow's.
+        assert_eq!(
+            calendar.next_valid_expiries(expiry_on(THURSDAY_2026_10_29) + 1, 2),
+            vec![
+                expiry_on(FRIDAY_2026_10_30),
+                expiry_on(FRIDAY_2026_10_30 + 1)
+            ]
+        );
+        assert!(!calendar.is_valid_expiry(expiry_on(THURSDAY_2026_10_29) + 1));
+    }
+
+    #[test]
+    fn test_weekly_expiries() {
+        let calendar = ExpiryCalendar {
+            time_of_day_seconds: EXPIRY_TIME_OF_DAY,
+            weekly: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            calendar.next_valid_expiries(expiry_on(THURSDAY_2026_10_29), 3),
+            vec![
+                expiry_on(FRIDAY_2026_10_30),
+                expiry_on(FRIDAY_2026_10_30 + 7),
+                expiry_on(FRIDAY_2026_10_30 + 14),
+            ]
+        );
+        // An expiry at `now` has already passed.
+        assert_eq!(
+            calendar.next_valid_expiries(expiry_on(FRIDAY_2026_10_30), 1),
+            vec![expiry_on(FRIDAY_2026_10_30 + 7)]
+        );
+        assert!(calendar.is_valid_expiry(expiry_on(FRIDAY_2026_10_23)));
+        assert!(!calendar.is_valid_expiry(expiry_on(THURSDAY_2026_10_29)));
+    }
+
+    #[test]
+    fn test_monthly_expiries() {
+        let calendar = ExpiryCalendar {
+            time_of_day_seconds: EXPIRY_TIME_OF_DAY,
+            monthly: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            calendar.next_valid_expiries(expiry_on(THURSDAY_2026_10_01), 4),
+            vec![
+                expiry_on(FRIDAY_2026_10_30),
+                expiry_on(FRIDAY_2026_11_27),
+                expiry_on(FRIDAY_2026_12_25),
+                expiry_on(FRIDAY_2027_01_29),
+            ]
+        );
+        // Only the last Friday of the month.
+        assert!(!calendar.is_valid_expiry(expiry_on(FRIDAY_2026_10_23)));
+        assert!(!calendar.is_valid_expiry(expiry_on(FRIDAY_2026_10_30) + 60));
+    }
+
+    #[test]
+    fn test_scale_to_quote_decimals() {
+        // Pyth's usual exponent.
+        assert_eq!(
+            scale_to_quote_decimals(17_500_000_000, -8).unwrap(),
+            175_000_000
+        );
+        assert_eq!(
+            scale_to_quote_decimals(175_000_000, -6).unwrap(),
+            175_000_000
+        );
+        // Positive exponents scale up.
+        assert_eq!(scale_to_quote_decimals(3, 2).unwrap(), 300_000_000);
+        assert_eq!(scale_to_quote_decimals(3, 0).unwrap(), 3_000_000);
+    }
+
+    #[test]
+    fn test_scale_to_quote_decimals_large_negative_expo() {
+        assert_eq!(scale_to_quote_decimals(u128::MAX, -50).unwrap(), 0);
+        assert_eq!(scale_to_quote_decimals(u128::MAX, i32::MIN).unwrap(), 0);
+        assert_eq!(
+            scale_to_quote_decimals(u128::MAX, -44).unwrap(),
+            (u128::MAX / 10u128.pow(38)) as u64
+        );
+    }
+
+    #[test]
+    fn test_scale_to_quote_decimals_overflow() {
+        assert!(scale_to_quote_decimals(u64::MAX as u128, 0).is_err());
+        assert!(scale_to_quote_decimals(u64::MAX as u128 + 1, -6).is_err());
+        assert!(scale_to_quote_decimals(1, 14).is_err());
+        assert!(scale_to_quote_decimals(1, 40).is_err());
+        assert!(scale_to_quote_decimals(1, i32::MAX).is_err());
+        assert_eq!(scale_to_quote_decimals(1, 13).unwrap(), 10u64.pow(19));
+    }
+
+    #[test]
+    fn test_scale_to_quote_decimals_rounds_to_zero() {
+        assert_eq!(scale_to_quote_decimals(99, -8).unwrap(), 0);
+        assert_eq!(scale_to_quote_decimals(199, -8).unwrap(), 1);
+        // A price that rounds to zero can't settle, its confidence can.
+        assert!(normalize_oracle_price(99, 99, -8).is_err());
+        assert_eq!(normalize_oracle_price(100, 99, -8).unwrap(), (1, 0));
+    }
+
+    #[test]
+    fn test_get_ratio_bps() {
+        assert_eq!(get_ratio_bps(1_750_000, 175_000_000), 100);
+        assert_eq!(get_ratio_bps(1, 0), u64::MAX);
+        // Ratios past u64 saturate rather than truncate.
+        assert_eq!(get_ratio_bps(u64::MAX as u128, 1), u64::MAX);
+        assert_eq!(get_ratio_bps(u128::MAX, 1), u64::MAX);
+        assert_eq!(
+            get_ratio_bps(u64::MAX as u128, u64::MAX as u128),
+            BPS_DENOMINATOR
+        );
+    }
+
+    #[test]
+    fn test_unrestricted_calendar() {
+        let calendar = ExpiryCalendar::default();
+        assert!(calendar.is_valid_expiry(12_345));
+        assert!(calendar.next_valid_expiries(0, 3).is_empty());
+    }
+
+    #[test]
+    fn test_set_underlying_oracle_rejects_secondary_feed() {
+        let primary = Pubkey::new_unique();
+        let secondary = Pubkey::new_unique();
+        let mut underlying = Underlying::default();
+        underlying.oracle = primary;
+        underlying.oracles[0] = primary;
+        underlying.oracles[1] = secondary;
+        underlying.oracle_count = 2;
+
+        let set_oracle = |oracle| AdminAction::SetUnderlyingOracle {
+            underlying: Pubkey::default(),
+            oracle,
+        };
+        assert!(apply_oracle_action(&mut underlying, set_oracle(secondary)).is_err());
+        assert_eq!(underlying.oracle, primary);
+
+        let replacement = Pubkey::new_unique();
+        apply_oracle_action(&mut underlying, set_oracle(replacement)).unwrap();
+        assert_eq!(underlying.oracle, replacement);
+        assert_eq!(underlying.oracles[0], replacement);
+        assert_eq!(underlying.oracles[1], secondary);
+    }
 }
@@ -53,6 +53,41 @@ export const setFeedPrice = async (oracleProgram, newPrice, priceFeed) => {
     accounts: { price: priceFeed },
   });
 };
+export const setFeedConfidence = async (
+  oracleProgram,
+  newConfidence,
+  priceFeed
+) => {
+  const info = await oracleProgram.provider.connection.getAccountInfo(
+    priceFeed
+  );
+  const data = parsePriceData(info.data);
+  await oracleProgram.rpc.setConfidence(
+    new BN(newConfidence * 10 ** -data.exponent),
+    {
+      accounts: { price: priceFeed },
+    }
+  );
+};
+export const setFeedTwap = async (oracleProgram, newTwap, priceFeed) => {
+  const info = await oracleProgram.provider.connection.getAccountInfo(
+    priceFeed
+  );
+  const data = parsePriceData(info.data);
+  await oracleProgram.rpc.setTwap(new BN(newTwap * 10 ** -data.exponent), {
+    accounts: { price: priceFeed },
+  });
+};
+export const setFeedStatus = async (oracleProgram, status, priceFeed) => {
+  await oracleProgram.rpc.setStatus(PriceStatus.indexOf(status), {
+    accounts: { price: priceFeed },
+  });
+};
+export const setFeedPubSlot = async (oracleProgram, pubSlot, priceFeed) => {
+  await oracleProgram.rpc.setPubSlot(new BN(pubSlot), {
+    accounts: { price: priceFeed },
+  });
+};
 export const getFeedData = async (oracleProgram, priceFeed) => {
   const info = await oracleProgram.provider.connection.getAccountInfo(
     priceFeed
@@ -305,6 +340,9 @@ module.exports = {
   PriceType,
   createPriceFeed,
   setFeedPrice,
+  setFeedConfidence,
+  setFeedStatus,
+  setFeedTwap,
   getFeedData,
   readBigInt64LE,
   readBigUInt64LE,
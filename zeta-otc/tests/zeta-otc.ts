@@ -11,10 +11,22 @@ import {
 import * as assert from "assert";
 import * as utils from "./utils";
 import { Token, TOKEN_PROGRAM_ID } from "@solana/spl-token";
-import { createPriceFeed } from "./oracle-utils";
+import {
+  createPriceFeed,
+  setFeedPrice,
+  setFeedConfidence,
+  setFeedStatus,
+  setFeedTwap,
+  setFeedPubSlot,
+} from "./oracle-utils";
 
 const OPTION_MINT_DECIMALS: number = 4;
 
+// The tests load a stand-in for the Metaplex program at its real id.
+const TOKEN_METADATA_PROGRAM_ID = new PublicKey(
+  "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+);
+
 function getMinLotSize(mintDecimals: number): number {
   if (mintDecimals < OPTION_MINT_DECIMALS) {
     throw Error("");
@@ -22,6 +34,134 @@ function getMinLotSize(mintDecimals: number): number {
   return 10 ** mintDecimals / 10 ** OPTION_MINT_DECIMALS;
 }
 
+// Runs `fn` and resolves with the first `eventName` event it emits.
+async function captureEvent(
+  program: Program<any>,
+  eventName: string,
+  fn: () => Promise<any>
+) {
+  let event = null;
+  let listener = program.addEventListener(eventName, (e) => {
+    event = e;
+  });
+  await fn();
+  while (event == null) {
+    await utils.sleep(100);
+  }
+  await program.removeEventListener(listener);
+  return event;
+}
+
+async function getMetadataAddress(mint: PublicKey) {
+  return await anchor.web3.PublicKey.findProgramAddress(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("metadata")),
+      TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+      mint.toBuffer(),
+    ],
+    TOKEN_METADATA_PROGRAM_ID
+  );
+}
+
+// Decodes the leading fields of a Metaplex metadata account.
+async function fetchMetadata(
+  connection: anchor.web3.Connection,
+  address: PublicKey
+) {
+  let data = (await connection.getAccountInfo(address)).data;
+  let offset = 1 + 32 + 32;
+  let readString = () => {
+    let length = data.readUInt32LE(offset);
+    let value = data.slice(offset + 4, offset + 4 + length).toString("utf8");
+    offset += 4 + length;
+    return value.replace(/\0+$/, "");
+  };
+  return {
+    key: data[0],
+    updateAuthority: new PublicKey(data.slice(1, 33)),
+    mint: new PublicKey(data.slice(33, 65)),
+    name: readString(),
+    symbol: readString(),
+    uri: readString(),
+  };
+}
+
+async function getOptionAddresses(
+  programId: PublicKey,
+  underlying: PublicKey,
+  count: number,
+  creator: PublicKey
+) {
+  let [optionAccount, optionAccountNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("option-account")),
+        underlying.toBuffer(),
+        new anchor.BN(count).toArrayLike(Buffer, "le", 8),
+      ],
+      programId
+    );
+  let [vault, vaultNonce] = await anchor.web3.PublicKey.findProgramAddress(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("vault")),
+      optionAccount.toBuffer(),
+    ],
+    programId
+  );
+  let [settlementVault, settlementVaultNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("settlement-vault")),
+        optionAccount.toBuffer(),
+      ],
+      programId
+    );
+  let [optionMint, optionMintNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("option-mint")),
+        optionAccount.toBuffer(),
+      ],
+      programId
+    );
+  let [userOptionTokenAccount, tokenAccountNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [optionMint.toBuffer(), creator.toBuffer()],
+      programId
+    );
+  let [writerMint, writerMintNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("writer-mint")),
+        optionAccount.toBuffer(),
+      ],
+      programId
+    );
+  let [userWriterTokenAccount, writerTokenAccountNonce] =
+    await anchor.web3.PublicKey.findProgramAddress(
+      [writerMint.toBuffer(), creator.toBuffer()],
+      programId
+    );
+  let [optionMetadata] = await getMetadataAddress(optionMint);
+  return {
+    optionAccount,
+    optionAccountNonce,
+    vault,
+    vaultNonce,
+    settlementVault,
+    settlementVaultNonce,
+    optionMint,
+    optionMintNonce,
+    userOptionTokenAccount,
+    tokenAccountNonce,
+    writerMint,
+    writerMintNonce,
+    userWriterTokenAccount,
+    writerTokenAccountNonce,
+    optionMetadata,
+  };
+}
+
 describe("zeta-otc", () => {
   // Configure the client to use the local cluster.
   let provider = anchor.Provider.env();
@@ -32,39 +172,109 @@ describe("zeta-otc", () => {
   const admin = Keypair.generate();
   const tokenMintAuthority = Keypair.generate();
   const mintKeypair = Keypair.generate();
+  const quoteMintKeypair = Keypair.generate();
   const otherUser = Keypair.generate();
+  const guardian = Keypair.generate();
   let otherUserOptionAccount: PublicKey;
   let otherUserTokenAddress: PublicKey;
+  let otherUserQuoteTokenAddress: PublicKey;
 
   let state: PublicKey;
   let mintAuthority: PublicKey;
   let vaultAuthority: PublicKey;
+  // Protocol fee accounts for the underlying and quote mints.
+  let treasury: PublicKey;
+  let quoteTreasury: PublicKey;
   let underlying: PublicKey;
   let token: Token;
+  let quoteToken: Token;
+  let userQuoteTokenAddress: PublicKey;
   let userTokenAddress: PublicKey;
   let vault: PublicKey;
   let optionAccount: PublicKey;
   let optionMint: PublicKey;
+  let settlementVault: PublicKey;
   let userOptionTokenAccount: PublicKey;
+  let writerMint: PublicKey;
+  let userWriterTokenAccount: PublicKey;
   let oracle: PublicKey;
+  // Admin pushed fallback feed, settlement needs both feeds to agree.
+  const adminPriceKeypair = Keypair.generate();
+  let adminPrice: PublicKey = adminPriceKeypair.publicKey;
+  let oracleQuorum = 2;
+  let maxOracleDeviationBps = 100;
 
   let collateralAmount = 1_000_000_000_000;
   let decimals = 9;
+  let quoteDecimals = 6;
   let minLotSize = getMinLotSize(decimals);
   let expectedOptionTokenSupply = collateralAmount / minLotSize;
-  // 10 seconds in future of creation.
-  let expirationOffset = 5;
+  // Leaves room to create every series before expiry.
+  let expirationOffset = 20;
   let expirationTs: number;
-  let settlementPriceThresholdSeconds = 5;
+  // Longest expiry the underlying accepts for new options.
+  let maxTenorSeconds = 24 * 60 * 60;
+  let settlementPriceThresholdSeconds = 30;
+  // Payouts unlock this long after a settlement price is set.
+  let settlementDisputeSeconds = 10;
+  // Queued admin actions can execute this long after they're queued.
+  let adminActionDelaySeconds = 5;
+  // Unclaimed payouts can be swept this long after settlement.
+  let claimPeriodSeconds = 30;
+  // Unexercised physical options lapse this long after payouts unlock.
+  let exercisePeriodSeconds = 20;
   let oraclePrice = 175;
+  let oracleConfidence = 0.1;
+  // Settles on confidence intervals of up to 1% of the price.
+  let maxConfidenceBps = 100;
+  let maxStalenessSlots = 100;
   let nativeOraclePrice = oraclePrice * 10 ** 6;
   let strike = new anchor.BN(150_000_000); // 150
+  // Quote tokens locked per put option at the strike.
+  let putCollateralPerOption =
+    (strike.toNumber() * 10 ** quoteDecimals) / 10 ** (6 + OPTION_MINT_DECIMALS);
+  let putOptionAmount = 100_000;
+  let putCollateralAmount = putOptionAmount * putCollateralPerOption;
+  let putOptionAccount: PublicKey;
+  let putOptionMint: PublicKey;
+  let putVault: PublicKey;
+  let putSettlementVault: PublicKey;
+  let putUserOptionTokenAccount: PublicKey;
+  let putWriterMint: PublicKey;
+  let putWriterTokenAccount: PublicKey;
+  // Written by the other user on top of the creator's puts.
+  let otherUserPutOptionAmount = 50_000;
+  let otherUserPutCollateralAmount =
+    otherUserPutOptionAmount * putCollateralPerOption;
+  let otherUserPutWriterTokenAccount: PublicKey;
+  // Put writer tokens sold by the owner to the other user.
+  let transferredPutWriterTokens = 25_000;
+  // Written and then closed out by the other user before expiry.
+  let closedOutPutOptionAmount = 20_000;
+  let otherUserPutOptionAccount: PublicKey;
+  // One whole physically settled call.
+  let physicalOptionAmount = 10 ** OPTION_MINT_DECIMALS;
+  let physicalCollateralAmount = physicalOptionAmount * minLotSize;
+  let physicalStrikePayment = strike.toNumber();
+  // Held by the other user, who never exercises.
+  let lapsedOptionAmount = physicalOptionAmount / 2;
+  let physical;
+  let otherUserPhysicalOptionAccount: PublicKey;
+  // 150 / 200 call spread, collateralized by the 50 strike width.
+  let spreadShortStrike = new anchor.BN(200_000_000);
+  let spreadCollateralPerOption =
+    ((spreadShortStrike.toNumber() - strike.toNumber()) *
+      10 ** quoteDecimals) /
+    10 ** (6 + OPTION_MINT_DECIMALS);
+  let spreadOptionAmount = 100_000;
+  let spreadCollateralAmount = spreadOptionAmount * spreadCollateralPerOption;
+  let spread;
 
   it("Create oracle price feed.", async () => {
     oracle = await createPriceFeed({
       oracleProgram: pythProgram,
       initPrice: oraclePrice,
-      confidence: 0.1,
+      confidence: oracleConfidence,
       keypair: utils.getOracleKeypair(),
       expo: -8,
     });
@@ -91,6 +301,27 @@ describe("zeta-otc", () => {
     );
   });
 
+  it("Create quote mint and mint to user.", async () => {
+    quoteToken = await utils.createMint(
+      provider.connection,
+      quoteMintKeypair,
+      (provider.wallet as anchor.Wallet).payer,
+      tokenMintAuthority.publicKey,
+      quoteDecimals
+    );
+
+    userQuoteTokenAddress = await quoteToken.createAssociatedTokenAccount(
+      provider.wallet.publicKey
+    );
+
+    await quoteToken.mintTo(
+      userQuoteTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      putCollateralAmount
+    );
+  });
+
   it("Initialize state", async () => {
     await program.provider.connection.confirmTransaction(
       await program.provider.connection.requestAirdrop(
@@ -126,24 +357,85 @@ describe("zeta-otc", () => {
       mintAuthNonce,
       vaultAuthNonce,
       settlementPriceThresholdSeconds,
+      settlementDisputeSeconds,
+      adminActionDelaySeconds,
+      claimPeriodSeconds,
+      exercisePeriodSeconds,
     };
 
-    await program.rpc.initializeState(args, {
-      accounts: {
-        state,
-        systemProgram: SystemProgram.programId,
-        admin: admin.publicKey,
-        mintAuthority,
-        vaultAuthority,
-      },
-      signers: [admin],
-    });
+    let accounts = {
+      state,
+      systemProgram: SystemProgram.programId,
+      admin: admin.publicKey,
+      mintAuthority,
+      vaultAuthority,
+      quoteMint: quoteToken.publicKey,
+      guardian: guardian.publicKey,
+    };
+
+    // Unclaimed funds can't be swept before anyone could claim them.
+    await utils.expectError(async () => {
+      await program.rpc.initializeState(
+        { ...args, claimPeriodSeconds: 0 },
+        { accounts, signers: [admin] }
+      );
+    }, "Invalid claim period");
+    await utils.expectError(async () => {
+      await program.rpc.initializeState(
+        { ...args, exercisePeriodSeconds: claimPeriodSeconds + 1 },
+        { accounts, signers: [admin] }
+      );
+    }, "Invalid exercise period");
+
+    await program.rpc.initializeState(args, { accounts, signers: [admin] });
 
     let stateAccount = await program.account.state.fetch(state);
     assert.ok(stateAccount.admin.equals(admin.publicKey));
     assert.ok(stateAccount.stateNonce == stateNonce);
     assert.ok(stateAccount.vaultAuthNonce == vaultAuthNonce);
     assert.ok(stateAccount.mintAuthNonce == mintAuthNonce);
+    assert.ok(stateAccount.quoteMint.equals(quoteToken.publicKey));
+    assert.ok(stateAccount.guardian.equals(guardian.publicKey));
+    assert.ok(
+      stateAccount.settlementDisputeSeconds == settlementDisputeSeconds
+    );
+  });
+
+  it("Initialize treasuries", async () => {
+    for (let mint of [token.publicKey, quoteToken.publicKey]) {
+      let [_treasury, treasuryNonce] =
+        await anchor.web3.PublicKey.findProgramAddress(
+          [
+            Buffer.from(anchor.utils.bytes.utf8.encode("treasury")),
+            mint.toBuffer(),
+          ],
+          program.programId
+        );
+
+      await program.rpc.initializeTreasury(treasuryNonce, {
+        accounts: {
+          state,
+          treasury: _treasury,
+          mint,
+          admin: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+        },
+        signers: [admin],
+      });
+
+      let treasuryInfo = await utils.getTokenAccountInfo(
+        provider.connection,
+        _treasury
+      );
+      assert.ok(treasuryInfo.owner.equals(state));
+      if (mint.equals(token.publicKey)) {
+        treasury = _treasury;
+      } else {
+        quoteTreasury = _treasury;
+      }
+    }
   });
 
   it("Initialize underlying", async () => {
@@ -160,27 +452,185 @@ describe("zeta-otc", () => {
 
     let args = {
       underlyingNonce,
+      symbol: "SOL",
+      maxConfidenceBps: new anchor.BN(maxConfidenceBps),
+      maxStalenessSlots: new anchor.BN(maxStalenessSlots),
     };
 
+    let accounts = {
+      state,
+      underlying,
+      mint: token.publicKey,
+      oracle: oracle,
+      listingAuthority: admin.publicKey,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+    };
+
+    // Symbols name option mints, so they must be short and alphanumeric.
+    await utils.expectError(async () => {
+      await program.rpc.initializeUnderlying(
+        { ...args, symbol: "SOL-PERP" },
+        { accounts, signers: [admin] }
+      );
+    }, "Invalid underlying symbol");
+
     await program.rpc.initializeUnderlying(args, {
+      accounts,
+      signers: [admin],
+    });
+
+    let underlyingAccount = await program.account.underlying.fetch(underlying);
+    assert.ok(underlyingAccount.underlyingNonce == underlyingNonce);
+    assert.ok(underlyingAccount.mint.equals(token.publicKey));
+    assert.ok(underlyingAccount.oracle.equals(oracle));
+    assert.ok(underlyingAccount.count.eq(new anchor.BN(0)));
+    assert.ok(
+      underlyingAccount.maxConfidenceBps.toNumber() == maxConfidenceBps
+    );
+    assert.ok(
+      underlyingAccount.maxStalenessSlots.toNumber() == maxStalenessSlots
+    );
+  });
+
+  async function setAdminPrice(price: number) {
+    await program.rpc.setAdminPrice(
+      new anchor.BN(price * 10 ** 6),
+      new anchor.BN(oracleConfidence * 10 ** 6),
+      {
+        accounts: {
+          adminPrice,
+          authority: admin.publicKey,
+        },
+        signers: [admin],
+      }
+    );
+  }
+
+  const getQueuedActionAddress = async () => {
+    let stateAccount = await program.account.state.fetch(state);
+    return await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("queued-action")),
+        stateAccount.queuedActionCount.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+  };
+
+  const queueAdminAction = async (action) => {
+    let [queuedAction, queuedActionNonce] = await getQueuedActionAddress();
+    await program.rpc.queueAdminAction(queuedActionNonce, action, {
       accounts: {
         state,
-        underlying,
-        mint: token.publicKey,
-        oracle: oracle,
+        queuedAction,
         admin: admin.publicKey,
         systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        rent: SYSVAR_RENT_PUBKEY,
       },
       signers: [admin],
     });
+    return queuedAction;
+  };
+
+  const executeUnderlyingAdminAction = async (queuedAction) => {
+    await program.rpc.executeAdminAction({
+      accounts: { state, queuedAction, admin: admin.publicKey },
+      remainingAccounts: [
+        { pubkey: underlying, isWritable: true, isSigner: false },
+      ],
+      signers: [admin],
+    });
+  };
+
+  it("Add admin price oracle to underlying", async () => {
+    await program.rpc.initializeAdminPrice({
+      accounts: {
+        adminPrice,
+        authority: admin.publicKey,
+        systemProgram: SystemProgram.programId,
+      },
+      signers: [admin, adminPriceKeypair],
+    });
+    await setAdminPrice(oraclePrice);
+
+    // Oracle changes go through the admin timelock.
+    let addOracleAction = await queueAdminAction({
+      addOracle: {
+        underlying,
+        oracle: adminPrice,
+        oracleType: { adminPrice: {} },
+      },
+    });
+    let invalidQuorumAction = await queueAdminAction({
+      setOracleQuorum: {
+        underlying,
+        oracleQuorum: 3,
+        maxOracleDeviationBps: new anchor.BN(maxOracleDeviationBps),
+      },
+    });
+    let quorumAction = await queueAdminAction({
+      setOracleQuorum: {
+        underlying,
+        oracleQuorum,
+        maxOracleDeviationBps: new anchor.BN(maxOracleDeviationBps),
+      },
+    });
+
+    await utils.expectError(async () => {
+      await executeUnderlyingAdminAction(addOracleAction);
+    }, "Admin action not ready");
+
+    let queuedActionInfo = await program.account.queuedAction.fetch(
+      quorumAction
+    );
+    await utils.sleepTillTime(queuedActionInfo.eta.toNumber());
+    await executeUnderlyingAdminAction(addOracleAction);
+
+    // The quorum can't exceed the number of feeds.
+    await utils.expectError(async () => {
+      await executeUnderlyingAdminAction(invalidQuorumAction);
+    }, "Invalid oracle quorum");
+    await program.rpc.cancelAdminAction({
+      accounts: {
+        state,
+        queuedAction: invalidQuorumAction,
+        admin: admin.publicKey,
+      },
+      signers: [admin],
+    });
+
+    await executeUnderlyingAdminAction(quorumAction);
 
     let underlyingAccount = await program.account.underlying.fetch(underlying);
-    assert.ok(underlyingAccount.underlyingNonce == underlyingNonce);
-    assert.ok(underlyingAccount.mint.equals(token.publicKey));
-    assert.ok(underlyingAccount.oracle.equals(oracle));
-    assert.ok(underlyingAccount.count.eq(new anchor.BN(0)));
+    assert.ok(underlyingAccount.oracleCount == 2);
+    assert.ok(underlyingAccount.oracles[0].equals(oracle));
+    assert.ok(underlyingAccount.oracles[1].equals(adminPrice));
+    assert.ok(underlyingAccount.oracleQuorum == oracleQuorum);
+  });
+
+  it("Set option limits", async () => {
+    let args = {
+      minMoneynessBps: new anchor.BN(5_000),
+      maxMoneynessBps: new anchor.BN(20_000),
+      strikeTickSize: new anchor.BN(1_000_000),
+      minTimeToExpirySeconds: new anchor.BN(5),
+      maxTenorSeconds: new anchor.BN(maxTenorSeconds),
+    };
+    let accounts = { state, underlying, listingAuthority: admin.publicKey };
+
+    await utils.expectError(async () => {
+      await program.rpc.setOptionLimits(
+        { ...args, maxMoneynessBps: new anchor.BN(4_000) },
+        { accounts, signers: [admin] }
+      );
+    }, "Invalid option limits");
+
+    await program.rpc.setOptionLimits(args, { accounts, signers: [admin] });
+
+    let underlyingAccount = await program.account.underlying.fetch(underlying);
+    assert.ok(underlyingAccount.strikeTickSize.eq(args.strikeTickSize));
+    assert.ok(underlyingAccount.maxTenorSeconds.eq(args.maxTenorSeconds));
   });
 
   it("Initialize option", async () => {
@@ -220,6 +670,16 @@ describe("zeta-otc", () => {
     optionMint = _optionMint;
     vault = _vault;
 
+    let [_settlementVault, settlementVaultNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(anchor.utils.bytes.utf8.encode("settlement-vault")),
+          optionAccount.toBuffer(),
+        ],
+        program.programId
+      );
+    settlementVault = _settlementVault;
+
     let [_userOptionTokenAccount, userOptionTokenAccountNonce] =
       await anchor.web3.PublicKey.findProgramAddress(
         [optionMint.toBuffer(), provider.wallet.publicKey.toBuffer()],
@@ -227,6 +687,25 @@ describe("zeta-otc", () => {
       );
     userOptionTokenAccount = _userOptionTokenAccount;
 
+    let [_writerMint, writerMintNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(anchor.utils.bytes.utf8.encode("writer-mint")),
+          optionAccount.toBuffer(),
+        ],
+        program.programId
+      );
+    writerMint = _writerMint;
+
+    let [_userWriterTokenAccount, writerTokenAccountNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [writerMint.toBuffer(), provider.wallet.publicKey.toBuffer()],
+        program.programId
+      );
+    userWriterTokenAccount = _userWriterTokenAccount;
+
+    let [optionMetadata] = await getMetadataAddress(optionMint);
+
     let expiry = new anchor.BN(123);
 
     let args = {
@@ -235,51 +714,77 @@ describe("zeta-otc", () => {
       optionMintNonce,
       tokenAccountNonce: userOptionTokenAccountNonce,
       vaultNonce,
+      settlementVaultNonce,
+      writerMintNonce,
+      writerTokenAccountNonce,
       expiry,
       strike,
+      shortStrike: new anchor.BN(0),
+      kind: { call: {} },
+      settlementMode: { cash: {} },
+      exerciseStyle: { european: {} },
+      settlementSource: { spot: {} },
+    };
+
+    let accounts = {
+      state,
+      underlying,
+      oracle,
+      vault,
+      vaultAuthority,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      treasury,
+      settlementMint: quoteToken.publicKey,
+      settlementVault,
+      collateralTokenAccount: userTokenAddress,
+      creator: provider.wallet.publicKey,
+      optionAccount,
+      mintAuthority,
+      optionMint,
+      userOptionTokenAccount,
+      writerMint,
+      userWriterTokenAccount,
+      optionMetadata,
+      metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
     };
 
     await utils.expectError(async () => {
-      await program.rpc.initializeOption(args, {
-        accounts: {
-          state,
-          underlying,
-          vault,
-          vaultAuthority,
-          underlyingMint: token.publicKey,
-          underlyingTokenAccount: userTokenAddress,
-          creator: provider.wallet.publicKey,
-          optionAccount,
-          mintAuthority,
-          optionMint,
-          userOptionTokenAccount,
-          systemProgram: SystemProgram.programId,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          rent: SYSVAR_RENT_PUBKEY,
-        },
-      });
+      await program.rpc.initializeOption(args, { accounts });
     }, "Option expiration must be in the future");
 
+    let limitViolations = [
+      { expiry: new anchor.BN(now + 3), error: "Expiry too soon" },
+      {
+        expiry: new anchor.BN(now + 2 * maxTenorSeconds),
+        error: "Expiry too far",
+      },
+      { strike: new anchor.BN(0), error: "Strike must be non zero" },
+      { strike: new anchor.BN(150_500_000), error: "Strike not on tick size" },
+      {
+        strike: new anchor.BN(50_000_000),
+        error: "Strike below min moneyness",
+      },
+      {
+        strike: new anchor.BN(400_000_000),
+        error: "Strike above max moneyness",
+      },
+    ];
+    for (let { error, ...invalid } of limitViolations) {
+      await utils.expectError(async () => {
+        await program.rpc.initializeOption(
+          { ...args, expiry: new anchor.BN(expirationTs), ...invalid },
+          { accounts }
+        );
+      }, error);
+    }
+
     args.expiry = new anchor.BN(expirationTs);
 
-    await program.rpc.initializeOption(args, {
-      accounts: {
-        state,
-        underlying,
-        vault,
-        vaultAuthority,
-        underlyingMint: token.publicKey,
-        underlyingTokenAccount: userTokenAddress,
-        creator: provider.wallet.publicKey,
-        optionAccount,
-        mintAuthority,
-        optionMint,
-        userOptionTokenAccount,
-        systemProgram: SystemProgram.programId,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        rent: SYSVAR_RENT_PUBKEY,
-      },
-    });
+    await program.rpc.initializeOption(args, { accounts });
 
     let optionAccountInfo = await program.account.optionAccount.fetch(
       optionAccount
@@ -297,6 +802,22 @@ describe("zeta-otc", () => {
         userOptionTokenAccountNonce
     );
     assert.ok(optionAccountInfo.remainingCollateral.toNumber() == 0);
+    assert.ok(optionAccountInfo.writerMint.equals(writerMint));
+
+    let metadata = await fetchMetadata(provider.connection, optionMetadata);
+    let expiryDate = new Date(expirationTs * 1000)
+      .toISOString()
+      .slice(0, 10)
+      .replace(/-/g, "");
+    assert.ok(metadata.name == `SOL-${expiryDate}-150-C`);
+    assert.ok(metadata.symbol == "SOL");
+    assert.ok(metadata.mint.equals(optionMint));
+    assert.ok(metadata.updateAuthority.equals(mintAuthority));
+    assert.ok(
+      metadata.uri ==
+        `zeta-otc://option/${optionAccount.toBase58()}` +
+          `?underlying_mint=${token.publicKey.toBase58()}`
+    );
 
     let vaultInfo = await utils.getTokenAccountInfo(provider.connection, vault);
     assert.ok(vaultInfo.amount.toNumber() == collateralAmount);
@@ -313,67 +834,554 @@ describe("zeta-otc", () => {
     assert.ok(mintInfo.supply.toNumber() == expectedOptionTokenSupply);
     assert.ok(mintInfo.mintAuthority.equals(mintAuthority));
 
+    // The creator also receives one writer token per option written.
+    let userWriterTokenAccountInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      userWriterTokenAccount
+    );
+    assert.ok(
+      userWriterTokenAccountInfo.amount.toNumber() == expectedOptionTokenSupply
+    );
+
     let underlyingAccount = await program.account.underlying.fetch(underlying);
     assert.ok(underlyingAccount.count.eq(new anchor.BN(1)));
   });
 
-  it("Burn options", async () => {
-    let burnAmount = new anchor.BN(expectedOptionTokenSupply / 2);
-    await program.rpc.burnOption(burnAmount, {
-      accounts: {
-        state,
-        underlying,
-        vault,
-        underlyingMint: token.publicKey,
-        underlyingTokenAccount: userTokenAddress,
-        creator: provider.wallet.publicKey,
-        optionAccount,
-        mintAuthority,
-        optionMint,
-        userOptionTokenAccount,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        vaultAuthority,
-      },
-    });
-
-    let userOptionTokenAccountInfo = await utils.getTokenAccountInfo(
-      provider.connection,
-      userOptionTokenAccount
+  it("Initialize put option", async () => {
+    let addresses = await getOptionAddresses(
+      program.programId,
+      underlying,
+      1,
+      provider.wallet.publicKey
     );
+    putOptionAccount = addresses.optionAccount;
+    putOptionMint = addresses.optionMint;
+    putVault = addresses.vault;
+    putSettlementVault = addresses.settlementVault;
+    putUserOptionTokenAccount = addresses.userOptionTokenAccount;
+    putWriterMint = addresses.writerMint;
+    putWriterTokenAccount = addresses.userWriterTokenAccount;
+
+    let args = {
+      collateralAmount: new anchor.BN(putCollateralAmount),
+      optionAccountNonce: addresses.optionAccountNonce,
+      optionMintNonce: addresses.optionMintNonce,
+      tokenAccountNonce: addresses.tokenAccountNonce,
+      vaultNonce: addresses.vaultNonce,
+      settlementVaultNonce: addresses.settlementVaultNonce,
+      writerMintNonce: addresses.writerMintNonce,
+      writerTokenAccountNonce: addresses.writerTokenAccountNonce,
+      expiry: new anchor.BN(expirationTs),
+      strike,
+      shortStrike: new anchor.BN(0),
+      kind: { put: {} },
+      settlementMode: { cash: {} },
+      exerciseStyle: { european: {} },
+      settlementSource: { spot: {} },
+    };
+
+    let accounts = {
+      state,
+      underlying,
+      oracle,
+      vault: putVault,
+      vaultAuthority,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      treasury,
+      settlementMint: token.publicKey,
+      settlementVault: putSettlementVault,
+      collateralTokenAccount: userTokenAddress,
+      creator: provider.wallet.publicKey,
+      optionAccount: putOptionAccount,
+      mintAuthority,
+      optionMint: putOptionMint,
+      userOptionTokenAccount: addresses.userOptionTokenAccount,
+      writerMint: putWriterMint,
+      userWriterTokenAccount: putWriterTokenAccount,
+      optionMetadata: addresses.optionMetadata,
+      metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+    };
 
+    // Puts must be collateralized in the quote mint.
+    await utils.expectError(async () => {
+      await program.rpc.initializeOption(args, { accounts });
+    }, "Invalid collateral mint");
+
+    accounts.collateralMint = quoteToken.publicKey;
+    accounts.collateralTokenAccount = userQuoteTokenAddress;
+    let initializedEvent = await captureEvent(
+      program,
+      "OptionInitialized",
+      () => program.rpc.initializeOption(args, { accounts })
+    );
+    assert.ok(initializedEvent.optionAccount.equals(putOptionAccount));
+    assert.deepEqual(initializedEvent.kind, { put: {} });
     assert.ok(
-      userOptionTokenAccountInfo.amount.toNumber() ==
-        expectedOptionTokenSupply / 2
+      initializedEvent.collateralAmount.toNumber() == putCollateralAmount
     );
 
-    let userTokenAccount = await utils.getTokenAccountInfo(
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      putOptionAccount
+    );
+    assert.ok(optionAccountInfo.kind.put != undefined);
+    assert.ok(optionAccountInfo.collateralMint.equals(quoteToken.publicKey));
+
+    let vaultInfo = await utils.getTokenAccountInfo(
       provider.connection,
-      userTokenAddress
+      putVault
     );
-    assert.ok(userTokenAccount.amount.toNumber() == collateralAmount / 2);
+    assert.ok(vaultInfo.amount.toNumber() == putCollateralAmount);
 
-    console.log("Remaining collateral = ", collateralAmount / 2);
-    console.log("Remaining token supply = ", expectedOptionTokenSupply / 2);
+    let mintInfo = await utils.getMintInfo(provider.connection, putOptionMint);
+    assert.ok(mintInfo.supply.toNumber() == putOptionAmount);
   });
 
-  let profitPerOption: number;
-  let transferAmount: number = 10000;
+  it("Other user writes more put options", async () => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        otherUser.publicKey,
+        10_000_000_000
+      ),
+      "confirmed"
+    );
 
-  it("Expire option.", async () => {
-    await utils.sleepTillTime(expirationTs);
+    otherUserQuoteTokenAddress = await quoteToken.createAssociatedTokenAccount(
+      otherUser.publicKey
+    );
+    await quoteToken.mintTo(
+      otherUserQuoteTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      otherUserPutCollateralAmount
+    );
 
-    await program.rpc.expireOption({
+    let putOptionToken = new Token(
+      provider.connection,
+      putOptionMint,
+      TOKEN_PROGRAM_ID,
+      (provider.wallet as anchor.Wallet).payer
+    );
+    otherUserPutOptionAccount = await putOptionToken.createAccount(
+      otherUser.publicKey
+    );
+
+    let putWriterToken = new Token(
+      provider.connection,
+      putWriterMint,
+      TOKEN_PROGRAM_ID,
+      (provider.wallet as anchor.Wallet).payer
+    );
+    otherUserPutWriterTokenAccount = await putWriterToken.createAccount(
+      otherUser.publicKey
+    );
+
+    await program.rpc.writeMoreOptions(
+      new anchor.BN(otherUserPutCollateralAmount),
+      {
+        accounts: {
+          state,
+          underlying,
+          vault: putVault,
+          underlyingMint: token.publicKey,
+          collateralMint: quoteToken.publicKey,
+          treasury: quoteTreasury,
+          collateralTokenAccount: otherUserQuoteTokenAddress,
+          writer: otherUser.publicKey,
+          optionAccount: putOptionAccount,
+          mintAuthority,
+          optionMint: putOptionMint,
+          userOptionTokenAccount: otherUserPutOptionAccount,
+          writerMint: putWriterMint,
+          userWriterTokenAccount: otherUserPutWriterTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [otherUser],
+      }
+    );
+
+    let writerMintInfo = await utils.getMintInfo(
+      provider.connection,
+      putWriterMint
+    );
+    assert.ok(
+      writerMintInfo.supply.toNumber() ==
+        putOptionAmount + otherUserPutOptionAmount
+    );
+    let writerTokenAccountInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      otherUserPutWriterTokenAccount
+    );
+    assert.ok(
+      writerTokenAccountInfo.amount.toNumber() == otherUserPutOptionAmount
+    );
+
+    let optionTokenAccountInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      otherUserPutOptionAccount
+    );
+    assert.ok(
+      optionTokenAccountInfo.amount.toNumber() == otherUserPutOptionAmount
+    );
+
+    let vaultInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      putVault
+    );
+    assert.ok(
+      vaultInfo.amount.toNumber() ==
+        putCollateralAmount + otherUserPutCollateralAmount
+    );
+  });
+
+  it("Other user closes out put options", async () => {
+    let closedOutCollateralAmount =
+      closedOutPutOptionAmount * putCollateralPerOption;
+    await quoteToken.mintTo(
+      otherUserQuoteTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      closedOutCollateralAmount
+    );
+
+    await program.rpc.writeMoreOptions(
+      new anchor.BN(closedOutCollateralAmount),
+      {
+        accounts: {
+          state,
+          underlying,
+          vault: putVault,
+          underlyingMint: token.publicKey,
+          collateralMint: quoteToken.publicKey,
+          treasury: quoteTreasury,
+          collateralTokenAccount: otherUserQuoteTokenAddress,
+          writer: otherUser.publicKey,
+          optionAccount: putOptionAccount,
+          mintAuthority,
+          optionMint: putOptionMint,
+          userOptionTokenAccount: otherUserPutOptionAccount,
+          writerMint: putWriterMint,
+          userWriterTokenAccount: otherUserPutWriterTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [otherUser],
+      }
+    );
+
+    // Holding both positions is enough to burn, no matter who created the
+    // series.
+    await program.rpc.burnOption(new anchor.BN(closedOutPutOptionAmount), {
       accounts: {
         state,
         underlying,
+        vault: putVault,
         underlyingMint: token.publicKey,
-        optionAccount,
+        collateralMint: quoteToken.publicKey,
+        collateralTokenAccount: otherUserQuoteTokenAddress,
+        authority: otherUser.publicKey,
+        optionAccount: putOptionAccount,
+        mintAuthority,
+        optionMint: putOptionMint,
+        userOptionTokenAccount: otherUserPutOptionAccount,
+        writerMint: putWriterMint,
+        userWriterTokenAccount: otherUserPutWriterTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+      signers: [otherUser],
+    });
+
+    let quoteTokenAccountInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      otherUserQuoteTokenAddress
+    );
+    assert.ok(
+      quoteTokenAccountInfo.amount.toNumber() == closedOutCollateralAmount
+    );
+
+    let optionTokenAccountInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      otherUserPutOptionAccount
+    );
+    assert.ok(
+      optionTokenAccountInfo.amount.toNumber() == otherUserPutOptionAmount
+    );
+    let writerTokenAccountInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      otherUserPutWriterTokenAccount
+    );
+    assert.ok(
+      writerTokenAccountInfo.amount.toNumber() == otherUserPutOptionAmount
+    );
+  });
+
+  it("Burn options", async () => {
+    let burnAmount = new anchor.BN(expectedOptionTokenSupply / 2);
+    let burnedEvent = await captureEvent(program, "OptionsBurned", () =>
+      program.rpc.burnOption(burnAmount, {
+        accounts: {
+          state,
+          underlying,
+          vault,
+          underlyingMint: token.publicKey,
+          collateralMint: token.publicKey,
+          collateralTokenAccount: userTokenAddress,
+          authority: provider.wallet.publicKey,
+          optionAccount,
+          mintAuthority,
+          optionMint,
+          userOptionTokenAccount,
+          writerMint,
+          userWriterTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          vaultAuthority,
+        },
+      })
+    );
+    assert.ok(burnedEvent.optionAccount.equals(optionAccount));
+    assert.ok(burnedEvent.optionAmount.eq(burnAmount));
+    assert.ok(burnedEvent.collateralAmount.toNumber() == collateralAmount / 2);
+
+    let userOptionTokenAccountInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      userOptionTokenAccount
+    );
+
+    assert.ok(
+      userOptionTokenAccountInfo.amount.toNumber() ==
+        expectedOptionTokenSupply / 2
+    );
+
+    let userTokenAccount = await utils.getTokenAccountInfo(
+      provider.connection,
+      userTokenAddress
+    );
+    assert.ok(userTokenAccount.amount.toNumber() == collateralAmount / 2);
+
+    console.log("Remaining collateral = ", collateralAmount / 2);
+    console.log("Remaining token supply = ", expectedOptionTokenSupply / 2);
+  });
+
+  it("Initialize physically settled call option", async () => {
+    physical = await getOptionAddresses(
+      program.programId,
+      underlying,
+      2,
+      provider.wallet.publicKey
+    );
+
+    await token.mintTo(
+      userTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      physicalCollateralAmount
+    );
+
+    let args = {
+      collateralAmount: new anchor.BN(physicalCollateralAmount),
+      optionAccountNonce: physical.optionAccountNonce,
+      optionMintNonce: physical.optionMintNonce,
+      tokenAccountNonce: physical.tokenAccountNonce,
+      vaultNonce: physical.vaultNonce,
+      settlementVaultNonce: physical.settlementVaultNonce,
+      writerMintNonce: physical.writerMintNonce,
+      writerTokenAccountNonce: physical.writerTokenAccountNonce,
+      expiry: new anchor.BN(expirationTs),
+      strike,
+      shortStrike: new anchor.BN(0),
+      kind: { call: {} },
+      settlementMode: { physical: {} },
+      exerciseStyle: { european: {} },
+      settlementSource: { spot: {} },
+    };
+
+    await program.rpc.initializeOption(args, {
+      accounts: {
+        state,
+        underlying,
         oracle,
-        optionMint,
-        vault,
+        vault: physical.vault,
+        vaultAuthority,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        treasury,
+        settlementMint: quoteToken.publicKey,
+        settlementVault: physical.settlementVault,
+        collateralTokenAccount: userTokenAddress,
+        creator: provider.wallet.publicKey,
+        optionAccount: physical.optionAccount,
+        mintAuthority,
+        optionMint: physical.optionMint,
+        userOptionTokenAccount: physical.userOptionTokenAccount,
+        writerMint: physical.writerMint,
+        userWriterTokenAccount: physical.userWriterTokenAccount,
+        optionMetadata: physical.optionMetadata,
+        metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
       },
     });
 
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      physical.optionAccount
+    );
+    assert.ok(optionAccountInfo.settlementMode.physical != undefined);
+    assert.ok(optionAccountInfo.settlementMint.equals(quoteToken.publicKey));
+  });
+
+  it("Initialize call spread option", async () => {
+    spread = await getOptionAddresses(
+      program.programId,
+      underlying,
+      3,
+      provider.wallet.publicKey
+    );
+
+    await quoteToken.mintTo(
+      userQuoteTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      spreadCollateralAmount
+    );
+
+    let args = {
+      collateralAmount: new anchor.BN(spreadCollateralAmount),
+      optionAccountNonce: spread.optionAccountNonce,
+      optionMintNonce: spread.optionMintNonce,
+      tokenAccountNonce: spread.tokenAccountNonce,
+      vaultNonce: spread.vaultNonce,
+      settlementVaultNonce: spread.settlementVaultNonce,
+      writerMintNonce: spread.writerMintNonce,
+      writerTokenAccountNonce: spread.writerTokenAccountNonce,
+      expiry: new anchor.BN(expirationTs),
+      strike: spreadShortStrike,
+      shortStrike: strike,
+      kind: { callSpread: {} },
+      settlementMode: { cash: {} },
+      exerciseStyle: { european: {} },
+      settlementSource: { twap: {} },
+    };
+
+    let accounts = {
+      state,
+      underlying,
+      oracle,
+      vault: spread.vault,
+      vaultAuthority,
+      underlyingMint: token.publicKey,
+      collateralMint: quoteToken.publicKey,
+      treasury: quoteTreasury,
+      settlementMint: quoteToken.publicKey,
+      settlementVault: spread.settlementVault,
+      collateralTokenAccount: userQuoteTokenAddress,
+      creator: provider.wallet.publicKey,
+      optionAccount: spread.optionAccount,
+      mintAuthority,
+      optionMint: spread.optionMint,
+      userOptionTokenAccount: spread.userOptionTokenAccount,
+      writerMint: spread.writerMint,
+      userWriterTokenAccount: spread.userWriterTokenAccount,
+      optionMetadata: spread.optionMetadata,
+      metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+    };
+
+    // The short call strike must be above the long call strike.
+    await utils.expectError(async () => {
+      await program.rpc.initializeOption(args, { accounts });
+    }, "Invalid spread strikes");
+
+    args.strike = strike;
+    args.shortStrike = spreadShortStrike;
+    await program.rpc.initializeOption(args, { accounts });
+
+    let vaultInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      spread.vault
+    );
+    assert.ok(vaultInfo.amount.toNumber() == spreadCollateralAmount);
+
+    let mintInfo = await utils.getMintInfo(provider.connection, spread.optionMint);
+    assert.ok(mintInfo.supply.toNumber() == spreadOptionAmount);
+
+    let metadata = await fetchMetadata(
+      provider.connection,
+      spread.optionMetadata
+    );
+    assert.ok(metadata.name.endsWith("-150/200-CS"));
+  });
+
+  let profitPerOption: number;
+  let transferAmount: number = 10000;
+
+  it("Expire option.", async () => {
+    await utils.sleepTillTime(expirationTs);
+
+    let accounts = {
+      state,
+      underlying,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      optionAccount,
+      oracle,
+      optionMint,
+      vault,
+    };
+    let remainingAccounts = [
+      { pubkey: adminPrice, isWritable: false, isSigner: false },
+    ];
+    await setAdminPrice(oraclePrice);
+
+    // A 2% confidence interval is too uncertain to settle on.
+    await setFeedConfidence(pythProgram, oraclePrice / 50, oracle);
+    await utils.expectError(async () => {
+      await program.rpc.expireOption({ accounts, remainingAccounts });
+    }, "Oracle confidence too wide, use expire option override");
+
+    await setFeedStatus(pythProgram, "Halted", oracle);
+    await utils.expectError(async () => {
+      await program.rpc.expireOption({ accounts, remainingAccounts });
+    }, "Oracle is not trading");
+
+    // Republishing also refreshes the feed's publish slot.
+    await setFeedStatus(pythProgram, "Trading", oracle);
+    await setFeedConfidence(pythProgram, oracleConfidence, oracle);
+
+    // A feed that hasn't published for `maxStalenessSlots` can't settle.
+    let slot = await provider.connection.getSlot();
+    await setFeedPubSlot(pythProgram, slot - maxStalenessSlots - 1, oracle);
+    await utils.expectError(async () => {
+      await program.rpc.expireOption({ accounts, remainingAccounts });
+    }, "Oracle price is stale");
+
+    // A negative print has no price in quote decimals.
+    await setFeedPrice(pythProgram, -oraclePrice, oracle);
+    await utils.expectError(async () => {
+      await program.rpc.expireOption({ accounts, remainingAccounts });
+    }, "Oracle price must be positive");
+    await setFeedPrice(pythProgram, oraclePrice, oracle);
+
+    // Both feeds are fresh but too far apart to agree.
+    await setAdminPrice(200);
+    await utils.expectError(async () => {
+      await program.rpc.expireOption({ accounts, remainingAccounts });
+    }, "Oracle quorum not met");
+
+    await setAdminPrice(oraclePrice);
+    let settledEvent = await captureEvent(program, "OptionSettled", () =>
+      program.rpc.expireOption({ accounts, remainingAccounts })
+    );
+    assert.ok(settledEvent.optionAccount.equals(optionAccount));
+    assert.deepEqual(settledEvent.method, { oracle: {} });
+    assert.ok(settledEvent.settlementPrice.toNumber() == nativeOraclePrice);
+    assert.ok(settledEvent.confidenceBps.toNumber() <= maxConfidenceBps);
+
     let optionAccountInfo = await program.account.optionAccount.fetch(
       optionAccount
     );
@@ -401,22 +1409,41 @@ describe("zeta-otc", () => {
     assert.ok(
       optionAccountInfo.settlementPrice.toNumber() == nativeOraclePrice
     );
+    assert.deepEqual(optionAccountInfo.status, { settled: {} });
   });
 
   it("Expire option override.", async () => {
     let overrideSettlementPrice = new anchor.BN(200 * Math.pow(10, 6));
 
-    await program.rpc.expireOptionOverride(overrideSettlementPrice, {
+    // The oracle price is already set, corrections go through a dispute.
+    await utils.expectError(async () => {
+      await program.rpc.expireOptionOverride(overrideSettlementPrice, {
+        accounts: {
+          state,
+          underlying,
+          underlyingMint: token.publicKey,
+          collateralMint: token.publicKey,
+          optionAccount,
+          optionMint,
+          vault,
+          settlementAuthority: admin.publicKey,
+        },
+        signers: [admin],
+      });
+    }, "Settlement price already set");
+
+    await program.rpc.disputeSettlement(overrideSettlementPrice, {
       accounts: {
         state,
         underlying,
         underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
         optionAccount,
         optionMint,
         vault,
-        admin: admin.publicKey,
+        authority: guardian.publicKey,
       },
-      signers: [admin],
+      signers: [guardian],
     });
 
     let optionAccountInfo = await program.account.optionAccount.fetch(
@@ -439,57 +1466,240 @@ describe("zeta-otc", () => {
 
     console.log(`Profit per option : ${profitPerOption}`);
     console.log(`Remaining collateral: ${underlyingAmount - totalProfit}`);
+    assert.deepEqual(optionAccountInfo.status, { disputed: {} });
     assert.ok(profitPerOption == optionAccountInfo.profitPerOption.toNumber());
     assert.ok(
       remainingCollateral == optionAccountInfo.remainingCollateral.toNumber()
     );
   });
 
-  it("Transfer token to other user", async () => {
-    let optionToken = new Token(
-      provider.connection,
-      optionMint,
-      TOKEN_PROGRAM_ID,
-      (provider.wallet as anchor.Wallet).payer
-    );
-
-    otherUserOptionAccount = await optionToken.createAccount(
-      otherUser.publicKey
-    );
-
-    await optionToken.transfer(
-      userOptionTokenAccount,
-      otherUserOptionAccount,
-      provider.wallet.publicKey,
-      [(provider.wallet as anchor.Wallet).payer],
-      transferAmount
-    );
+  let putRemainingCollateral: number;
 
-    otherUserTokenAddress = await token.createAssociatedTokenAccount(
-      otherUser.publicKey
-    );
-  });
+  it("Expire put option override.", async () => {
+    let overrideSettlementPrice = new anchor.BN(120 * Math.pow(10, 6));
 
-  it("Other user exercise option.", async () => {
-    await program.rpc.exerciseOption({
+    // Mistyped override, corrected by the guardian below.
+    await program.rpc.expireOptionOverride(new anchor.BN(100 * 10 ** 6), {
       accounts: {
         state,
         underlying,
-        vault,
         underlyingMint: token.publicKey,
-        underlyingTokenAccount: otherUserTokenAddress,
-        authority: otherUser.publicKey,
-        optionAccount,
-        optionMint,
-        userOptionTokenAccount: otherUserOptionAccount,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        vaultAuthority,
+        collateralMint: quoteToken.publicKey,
+        optionAccount: putOptionAccount,
+        optionMint: putOptionMint,
+        vault: putVault,
+        settlementAuthority: admin.publicKey,
       },
-      signers: [otherUser],
+      signers: [admin],
     });
 
-    let expectedTokenBalance = profitPerOption * transferAmount;
-    console.log(`Expected token balance : ${expectedTokenBalance}`);
+    let disputeAccounts = {
+      state,
+      underlying,
+      underlyingMint: token.publicKey,
+      collateralMint: quoteToken.publicKey,
+      optionAccount: putOptionAccount,
+      optionMint: putOptionMint,
+      vault: putVault,
+      authority: otherUser.publicKey,
+    };
+    await utils.expectError(async () => {
+      await program.rpc.disputeSettlement(overrideSettlementPrice, {
+        accounts: disputeAccounts,
+        signers: [otherUser],
+      });
+    }, "Unauthorized settlement disputer");
+
+    await program.rpc.disputeSettlement(overrideSettlementPrice, {
+      accounts: { ...disputeAccounts, authority: guardian.publicKey },
+      signers: [guardian],
+    });
+
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      putOptionAccount
+    );
+
+    // (Strike - settlement) * contract size in quote tokens.
+    let putProfitPerOption =
+      ((strike.toNumber() - overrideSettlementPrice.toNumber()) *
+        10 ** quoteDecimals) /
+      10 ** (6 + OPTION_MINT_DECIMALS);
+    let remainingCollateral =
+      putCollateralAmount +
+      otherUserPutCollateralAmount -
+      putProfitPerOption * (putOptionAmount + otherUserPutOptionAmount);
+    putRemainingCollateral = remainingCollateral;
+
+    assert.deepEqual(optionAccountInfo.status, { disputed: {} });
+    assert.ok(
+      putProfitPerOption == optionAccountInfo.profitPerOption.toNumber()
+    );
+    assert.ok(
+      remainingCollateral == optionAccountInfo.remainingCollateral.toNumber()
+    );
+  });
+
+  it("Expire physically settled call option override.", async () => {
+    let overrideSettlementPrice = new anchor.BN(200 * Math.pow(10, 6));
+
+    await program.rpc.expireOptionOverride(overrideSettlementPrice, {
+      accounts: {
+        state,
+        underlying,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        optionAccount: physical.optionAccount,
+        optionMint: physical.optionMint,
+        vault: physical.vault,
+        settlementAuthority: admin.publicKey,
+      },
+      signers: [admin],
+    });
+
+    // In the money physical options deliver the whole lot.
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      physical.optionAccount
+    );
+    assert.ok(optionAccountInfo.profitPerOption.toNumber() == minLotSize);
+    assert.ok(optionAccountInfo.remainingCollateral.toNumber() == 0);
+  });
+
+  it("Expire call spread option on the oracle TWAP.", async () => {
+    // The TWAP settles above the short strike, so the payoff is capped at the
+    // width even though the spot price is below it.
+    let twap = 250;
+    await setFeedTwap(pythProgram, twap, oracle);
+    await setAdminPrice(twap);
+
+    await program.rpc.expireOption({
+      accounts: {
+        state,
+        underlying,
+        underlyingMint: token.publicKey,
+        collateralMint: quoteToken.publicKey,
+        optionAccount: spread.optionAccount,
+        oracle,
+        optionMint: spread.optionMint,
+        vault: spread.vault,
+      },
+      remainingAccounts: [
+        { pubkey: adminPrice, isWritable: false, isSigner: false },
+      ],
+    });
+
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      spread.optionAccount
+    );
+    assert.ok(
+      optionAccountInfo.settlementPrice.toNumber() == twap * Math.pow(10, 6)
+    );
+    assert.ok(
+      optionAccountInfo.profitPerOption.toNumber() == spreadCollateralPerOption
+    );
+    assert.ok(optionAccountInfo.remainingCollateral.toNumber() == 0);
+  });
+
+  it("Transfer token to other user", async () => {
+    let optionToken = new Token(
+      provider.connection,
+      optionMint,
+      TOKEN_PROGRAM_ID,
+      (provider.wallet as anchor.Wallet).payer
+    );
+
+    otherUserOptionAccount = await optionToken.createAccount(
+      otherUser.publicKey
+    );
+
+    await optionToken.transfer(
+      userOptionTokenAccount,
+      otherUserOptionAccount,
+      provider.wallet.publicKey,
+      [(provider.wallet as anchor.Wallet).payer],
+      transferAmount
+    );
+
+    otherUserTokenAddress = await token.createAssociatedTokenAccount(
+      otherUser.publicKey
+    );
+  });
+
+  it("Settlement dispute window blocks payouts.", async () => {
+    let accounts = {
+      state,
+      underlying,
+      vault: physical.vault,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      treasury,
+      collateralTokenAccount: userTokenAddress,
+      oracle,
+      settlementMint: quoteToken.publicKey,
+      settlementVault: physical.settlementVault,
+      settlementTokenAccount: userQuoteTokenAddress,
+      authority: provider.wallet.publicKey,
+      optionAccount: physical.optionAccount,
+      optionMint: physical.optionMint,
+      userOptionTokenAccount: physical.userOptionTokenAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      vaultAuthority,
+    };
+    await utils.expectError(async () => {
+      await program.rpc.exerciseOption({ accounts });
+    }, "Settlement dispute window active");
+
+    // The call spread was the last series to settle.
+    let spreadAccountInfo = await program.account.optionAccount.fetch(
+      spread.optionAccount
+    );
+    await utils.sleepTillTime(
+      spreadAccountInfo.settlementTs.toNumber() + settlementDisputeSeconds
+    );
+
+    let disputeAccounts = {
+      state,
+      underlying,
+      underlyingMint: token.publicKey,
+      collateralMint: quoteToken.publicKey,
+      optionAccount: putOptionAccount,
+      optionMint: putOptionMint,
+      vault: putVault,
+      authority: guardian.publicKey,
+    };
+    await utils.expectError(async () => {
+      await program.rpc.disputeSettlement(new anchor.BN(130 * 10 ** 6), {
+        accounts: disputeAccounts,
+        signers: [guardian],
+      });
+    }, "Settlement dispute window closed");
+  });
+
+  it("Other user exercise option.", async () => {
+    await program.rpc.exerciseOption({
+      accounts: {
+        state,
+        underlying,
+        vault,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        treasury,
+        collateralTokenAccount: otherUserTokenAddress,
+        oracle,
+        settlementMint: quoteToken.publicKey,
+        settlementVault,
+        settlementTokenAccount: otherUserQuoteTokenAddress,
+        authority: otherUser.publicKey,
+        optionAccount,
+        optionMint,
+        userOptionTokenAccount: otherUserOptionAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+      signers: [otherUser],
+    });
+
+    let expectedTokenBalance = profitPerOption * transferAmount;
+    console.log(`Expected token balance : ${expectedTokenBalance}`);
 
     let userTokenAccountInfo = await utils.getTokenAccountInfo(
       provider.connection,
@@ -516,21 +1726,33 @@ describe("zeta-otc", () => {
     let optionBalance = userOptionTokenAccountInfo.amount.toNumber();
     let expectedTokenBalanceDiff = optionBalance * profitPerOption;
 
-    await program.rpc.exerciseOption({
-      accounts: {
-        state,
-        underlying,
-        vault,
-        underlyingMint: token.publicKey,
-        underlyingTokenAccount: userTokenAddress,
-        authority: provider.wallet.publicKey,
-        optionAccount,
-        optionMint,
-        userOptionTokenAccount,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        vaultAuthority,
-      },
-    });
+    let exercisedEvent = await captureEvent(program, "OptionExercised", () =>
+      program.rpc.exerciseOption({
+        accounts: {
+          state,
+          underlying,
+          vault,
+          underlyingMint: token.publicKey,
+          collateralMint: token.publicKey,
+          treasury,
+          collateralTokenAccount: userTokenAddress,
+          oracle,
+          settlementMint: quoteToken.publicKey,
+          settlementVault,
+          settlementTokenAccount: userQuoteTokenAddress,
+          authority: provider.wallet.publicKey,
+          optionAccount,
+          optionMint,
+          userOptionTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          vaultAuthority,
+        },
+      })
+    );
+    assert.ok(exercisedEvent.holder.equals(provider.wallet.publicKey));
+    assert.ok(exercisedEvent.optionAmount.toNumber() == optionBalance);
+    assert.ok(exercisedEvent.payout.toNumber() == expectedTokenBalanceDiff);
+    assert.ok(!exercisedEvent.early);
 
     userTokenAccount = await utils.getTokenAccountInfo(
       provider.connection,
@@ -548,21 +1770,33 @@ describe("zeta-otc", () => {
   });
 
   it("Owner collects remaining collateral", async () => {
-    await program.rpc.collectRemainingCollateral({
-      accounts: {
-        state,
-        underlying,
-        vault,
-        underlyingMint: token.publicKey,
-        underlyingTokenAccount: userTokenAddress,
-        creator: provider.wallet.publicKey,
-        optionAccount,
-        optionMint,
-        userOptionTokenAccount,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        vaultAuthority,
-      },
-    });
+    let collectedEvent = await captureEvent(
+      program,
+      "CollateralCollected",
+      () =>
+        program.rpc.collectRemainingCollateral({
+          accounts: {
+            state,
+            underlying,
+            vault,
+            underlyingMint: token.publicKey,
+            collateralMint: token.publicKey,
+            treasury,
+            collateralTokenAccount: userTokenAddress,
+            settlementVault,
+            settlementTokenAccount: userQuoteTokenAddress,
+            writer: provider.wallet.publicKey,
+            optionAccount,
+            optionMint,
+            writerMint,
+            userWriterTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            vaultAuthority,
+          },
+        })
+    );
+    assert.ok(collectedEvent.optionAccount.equals(optionAccount));
+    assert.ok(collectedEvent.writer.equals(provider.wallet.publicKey));
 
     let vaultInfo = await utils.getTokenAccountInfo(provider.connection, vault);
     assert.ok(vaultInfo.amount.toNumber() == 0);
@@ -571,12 +1805,13 @@ describe("zeta-otc", () => {
       optionAccount
     );
     assert.ok(optionAccountInfo.remainingCollateral.toNumber() == 0);
-    try {
-      await utils.getTokenAccountInfo(
-        provider.connection,
-        userOptionTokenAccount
-      );
-    } catch (e) {}
+    // Every option was exercised, so the last writer closes the series.
+    assert.deepEqual(optionAccountInfo.status, { closed: {} });
+    let writerMintInfo = await utils.getMintInfo(
+      provider.connection,
+      writerMint
+    );
+    assert.ok(writerMintInfo.supply.toNumber() == 0);
 
     let userTokenAccount = await utils.getTokenAccountInfo(
       provider.connection,
@@ -587,4 +1822,1495 @@ describe("zeta-otc", () => {
         collateralAmount - profitPerOption * transferAmount
     );
   });
+
+  it("Owner exercises physically settled call option.", async () => {
+    let physicalOptionToken = new Token(
+      provider.connection,
+      physical.optionMint,
+      TOKEN_PROGRAM_ID,
+      (provider.wallet as anchor.Wallet).payer
+    );
+    otherUserPhysicalOptionAccount = await physicalOptionToken.createAccount(
+      otherUser.publicKey
+    );
+    await physicalOptionToken.transfer(
+      physical.userOptionTokenAccount,
+      otherUserPhysicalOptionAccount,
+      provider.wallet.publicKey,
+      [(provider.wallet as anchor.Wallet).payer],
+      lapsedOptionAmount
+    );
+
+    await quoteToken.mintTo(
+      userQuoteTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      physicalStrikePayment
+    );
+
+    let prevTokenBalance = (
+      await utils.getTokenAccountInfo(provider.connection, userTokenAddress)
+    ).amount.toNumber();
+    let prevQuoteBalance = (
+      await utils.getTokenAccountInfo(
+        provider.connection,
+        userQuoteTokenAddress
+      )
+    ).amount.toNumber();
+
+    await program.rpc.exerciseOption({
+      accounts: {
+        state,
+        underlying,
+        vault: physical.vault,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        treasury,
+        collateralTokenAccount: userTokenAddress,
+        oracle,
+        settlementMint: quoteToken.publicKey,
+        settlementVault: physical.settlementVault,
+        settlementTokenAccount: userQuoteTokenAddress,
+        authority: provider.wallet.publicKey,
+        optionAccount: physical.optionAccount,
+        optionMint: physical.optionMint,
+        userOptionTokenAccount: physical.userOptionTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+    });
+
+    let tokenBalance = (
+      await utils.getTokenAccountInfo(provider.connection, userTokenAddress)
+    ).amount.toNumber();
+    let quoteBalance = (
+      await utils.getTokenAccountInfo(
+        provider.connection,
+        userQuoteTokenAddress
+      )
+    ).amount.toNumber();
+    assert.ok(tokenBalance - prevTokenBalance == physicalCollateralAmount / 2);
+    assert.ok(prevQuoteBalance - quoteBalance == physicalStrikePayment / 2);
+
+    let settlementVaultInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      physical.settlementVault
+    );
+    assert.ok(
+      settlementVaultInfo.amount.toNumber() == physicalStrikePayment / 2
+    );
+  });
+
+  it("Unexercised physical options lapse.", async () => {
+    let collectAccounts = {
+      state,
+      underlying,
+      vault: physical.vault,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      treasury,
+      collateralTokenAccount: userTokenAddress,
+      settlementVault: physical.settlementVault,
+      settlementTokenAccount: userQuoteTokenAddress,
+      writer: provider.wallet.publicKey,
+      optionAccount: physical.optionAccount,
+      optionMint: physical.optionMint,
+      writerMint: physical.writerMint,
+      userWriterTokenAccount: physical.userWriterTokenAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      vaultAuthority,
+    };
+
+    // The other user's lot stays reserved while they can still exercise.
+    await utils.expectError(async () => {
+      await program.rpc.collectRemainingCollateral({
+        accounts: collectAccounts,
+      });
+    }, "Physical options not fully exercised");
+
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      physical.optionAccount
+    );
+    let exerciseDeadline =
+      Math.max(
+        optionAccountInfo.expiry.toNumber(),
+        optionAccountInfo.settlementTs.toNumber() + settlementDisputeSeconds
+      ) + exercisePeriodSeconds;
+    await utils.sleepTillTime(exerciseDeadline);
+
+    await utils.expectError(async () => {
+      await program.rpc.exerciseOption({
+        accounts: {
+          state,
+          underlying,
+          vault: physical.vault,
+          underlyingMint: token.publicKey,
+          collateralMint: token.publicKey,
+          treasury,
+          collateralTokenAccount: otherUserTokenAddress,
+          oracle,
+          settlementMint: quoteToken.publicKey,
+          settlementVault: physical.settlementVault,
+          settlementTokenAccount: otherUserQuoteTokenAddress,
+          authority: otherUser.publicKey,
+          optionAccount: physical.optionAccount,
+          optionMint: physical.optionMint,
+          userOptionTokenAccount: otherUserPhysicalOptionAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          vaultAuthority,
+        },
+        signers: [otherUser],
+      });
+    }, "Exercise period over");
+  });
+
+  it("Owner collects physical settlement proceeds", async () => {
+    let prevTokenBalance = (
+      await utils.getTokenAccountInfo(provider.connection, userTokenAddress)
+    ).amount.toNumber();
+    let prevQuoteBalance = (
+      await utils.getTokenAccountInfo(
+        provider.connection,
+        userQuoteTokenAddress
+      )
+    ).amount.toNumber();
+
+    await program.rpc.collectRemainingCollateral({
+      accounts: {
+        state,
+        underlying,
+        vault: physical.vault,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        treasury,
+        collateralTokenAccount: userTokenAddress,
+        settlementVault: physical.settlementVault,
+        settlementTokenAccount: userQuoteTokenAddress,
+        writer: provider.wallet.publicKey,
+        optionAccount: physical.optionAccount,
+        optionMint: physical.optionMint,
+        writerMint: physical.writerMint,
+        userWriterTokenAccount: physical.userWriterTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+    });
+
+    let quoteBalance = (
+      await utils.getTokenAccountInfo(
+        provider.connection,
+        userQuoteTokenAddress
+      )
+    ).amount.toNumber();
+    assert.ok(quoteBalance - prevQuoteBalance == physicalStrikePayment / 2);
+
+    // The lapsed options' collateral returns to the writer.
+    let tokenBalance = (
+      await utils.getTokenAccountInfo(provider.connection, userTokenAddress)
+    ).amount.toNumber();
+    assert.ok(
+      tokenBalance - prevTokenBalance == lapsedOptionAmount * minLotSize
+    );
+  });
+
+  it("Owner exercises American call option before expiry.", async () => {
+    let american = await getOptionAddresses(
+      program.programId,
+      underlying,
+      4,
+      provider.wallet.publicKey
+    );
+    let americanOptionAmount = 10 ** OPTION_MINT_DECIMALS;
+    let americanCollateralAmount = americanOptionAmount * minLotSize;
+
+    await token.mintTo(
+      userTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      americanCollateralAmount
+    );
+
+    let args = {
+      collateralAmount: new anchor.BN(americanCollateralAmount),
+      optionAccountNonce: american.optionAccountNonce,
+      optionMintNonce: american.optionMintNonce,
+      tokenAccountNonce: american.tokenAccountNonce,
+      vaultNonce: american.vaultNonce,
+      settlementVaultNonce: american.settlementVaultNonce,
+      writerMintNonce: american.writerMintNonce,
+      writerTokenAccountNonce: american.writerTokenAccountNonce,
+      expiry: new anchor.BN(Date.now() / 1000 + 1000),
+      strike,
+      shortStrike: new anchor.BN(0),
+      kind: { call: {} },
+      settlementMode: { cash: {} },
+      exerciseStyle: { american: {} },
+      settlementSource: { spot: {} },
+    };
+
+    await program.rpc.initializeOption(args, {
+      accounts: {
+        state,
+        underlying,
+        oracle,
+        vault: american.vault,
+        vaultAuthority,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        treasury,
+        settlementMint: quoteToken.publicKey,
+        settlementVault: american.settlementVault,
+        collateralTokenAccount: userTokenAddress,
+        creator: provider.wallet.publicKey,
+        optionAccount: american.optionAccount,
+        mintAuthority,
+        optionMint: american.optionMint,
+        userOptionTokenAccount: american.userOptionTokenAccount,
+        writerMint: american.writerMint,
+        userWriterTokenAccount: american.userWriterTokenAccount,
+        optionMetadata: american.optionMetadata,
+        metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      },
+    });
+
+    let exerciseAccounts = {
+      state,
+      underlying,
+      vault: american.vault,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      treasury,
+      collateralTokenAccount: userTokenAddress,
+      oracle,
+      settlementMint: quoteToken.publicKey,
+      settlementVault: american.settlementVault,
+      settlementTokenAccount: userQuoteTokenAddress,
+      authority: provider.wallet.publicKey,
+      optionAccount: american.optionAccount,
+      optionMint: american.optionMint,
+      userOptionTokenAccount: american.userOptionTokenAccount,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      vaultAuthority,
+    };
+
+    // The live price has to be as trustworthy as a settlement print.
+    await setFeedStatus(pythProgram, "Halted", oracle);
+    await utils.expectError(async () => {
+      await program.rpc.exerciseOption({ accounts: exerciseAccounts });
+    }, "Oracle is not trading");
+    await setFeedStatus(pythProgram, "Trading", oracle);
+    await setFeedPrice(pythProgram, oraclePrice, oracle);
+
+    let prevTokenBalance = (
+      await utils.getTokenAccountInfo(provider.connection, userTokenAddress)
+    ).amount.toNumber();
+
+    await program.rpc.exerciseOption({ accounts: exerciseAccounts });
+
+    // Paid out at the live oracle price, not a settlement price.
+    let earlyProfitPerOption = Math.floor(
+      (minLotSize * (nativeOraclePrice - strike.toNumber())) / nativeOraclePrice
+    );
+    let tokenBalance = (
+      await utils.getTokenAccountInfo(provider.connection, userTokenAddress)
+    ).amount.toNumber();
+    assert.ok(
+      tokenBalance - prevTokenBalance ==
+        earlyProfitPerOption * americanOptionAmount
+    );
+
+    let mintInfo = await utils.getMintInfo(
+      provider.connection,
+      american.optionMint
+    );
+    assert.ok(mintInfo.supply.toNumber() == 0);
+
+    let vaultInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      american.vault
+    );
+    assert.ok(
+      vaultInfo.amount.toNumber() ==
+        americanCollateralAmount - earlyProfitPerOption * americanOptionAmount
+    );
+
+    // New writers can't pool full lots with the under-backed writer tokens.
+    await token.mintTo(
+      userTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      americanCollateralAmount
+    );
+    await utils.expectError(async () => {
+      await program.rpc.writeMoreOptions(
+        new anchor.BN(americanCollateralAmount),
+        {
+          accounts: {
+            state,
+            underlying,
+            vault: american.vault,
+            underlyingMint: token.publicKey,
+            collateralMint: token.publicKey,
+            treasury,
+            collateralTokenAccount: userTokenAddress,
+            writer: provider.wallet.publicKey,
+            optionAccount: american.optionAccount,
+            mintAuthority,
+            optionMint: american.optionMint,
+            userOptionTokenAccount: american.userOptionTokenAccount,
+            writerMint: american.writerMint,
+            userWriterTokenAccount: american.userWriterTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+        }
+      );
+    }, "Series has early exercises");
+  });
+
+  it("Owner transfers put writer tokens to other user.", async () => {
+    let putWriterToken = new Token(
+      provider.connection,
+      putWriterMint,
+      TOKEN_PROGRAM_ID,
+      (provider.wallet as anchor.Wallet).payer
+    );
+    await putWriterToken.transfer(
+      putWriterTokenAccount,
+      otherUserPutWriterTokenAccount,
+      provider.wallet.publicKey,
+      [(provider.wallet as anchor.Wallet).payer],
+      transferredPutWriterTokens
+    );
+  });
+
+  it("Writer token holders collect put collateral pro rata.", async () => {
+    let totalPutOptions = putOptionAmount + otherUserPutOptionAmount;
+    let otherUserShare = Math.floor(
+      (putRemainingCollateral *
+        (otherUserPutOptionAmount + transferredPutWriterTokens)) /
+        totalPutOptions
+    );
+    let ownerShare = putRemainingCollateral - otherUserShare;
+
+    let prevQuoteBalance = (
+      await utils.getTokenAccountInfo(
+        provider.connection,
+        otherUserQuoteTokenAddress
+      )
+    ).amount.toNumber();
+
+    await program.rpc.collectRemainingCollateral({
+      accounts: {
+        state,
+        underlying,
+        vault: putVault,
+        underlyingMint: token.publicKey,
+        collateralMint: quoteToken.publicKey,
+        treasury: quoteTreasury,
+        collateralTokenAccount: otherUserQuoteTokenAddress,
+        settlementVault: putSettlementVault,
+        settlementTokenAccount: otherUserTokenAddress,
+        writer: otherUser.publicKey,
+        optionAccount: putOptionAccount,
+        optionMint: putOptionMint,
+        writerMint: putWriterMint,
+        userWriterTokenAccount: otherUserPutWriterTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+      signers: [otherUser],
+    });
+
+    let quoteBalance = (
+      await utils.getTokenAccountInfo(
+        provider.connection,
+        otherUserQuoteTokenAddress
+      )
+    ).amount.toNumber();
+    assert.ok(quoteBalance - prevQuoteBalance == otherUserShare);
+
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      putOptionAccount
+    );
+    assert.ok(optionAccountInfo.remainingCollateral.toNumber() == ownerShare);
+
+    await program.rpc.collectRemainingCollateral({
+      accounts: {
+        state,
+        underlying,
+        vault: putVault,
+        underlyingMint: token.publicKey,
+        collateralMint: quoteToken.publicKey,
+        treasury: quoteTreasury,
+        collateralTokenAccount: userQuoteTokenAddress,
+        settlementVault: putSettlementVault,
+        settlementTokenAccount: userTokenAddress,
+        writer: provider.wallet.publicKey,
+        optionAccount: putOptionAccount,
+        optionMint: putOptionMint,
+        writerMint: putWriterMint,
+        userWriterTokenAccount: putWriterTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+    });
+
+    optionAccountInfo = await program.account.optionAccount.fetch(
+      putOptionAccount
+    );
+    assert.ok(optionAccountInfo.remainingCollateral.toNumber() == 0);
+
+    let writerMintInfo = await utils.getMintInfo(
+      provider.connection,
+      putWriterMint
+    );
+    assert.ok(writerMintInfo.supply.toNumber() == 0);
+  });
+
+  it("Keeper auto exercises put holders.", async () => {
+    let putProfitPerOption = (
+      await program.account.optionAccount.fetch(putOptionAccount)
+    ).profitPerOption.toNumber();
+
+    let holders = [
+      {
+        optionTokenAccount: putUserOptionTokenAccount,
+        quoteTokenAccount: userQuoteTokenAddress,
+        owner: (provider.wallet as anchor.Wallet).payer,
+        amount: putOptionAmount,
+      },
+      {
+        optionTokenAccount: otherUserPutOptionAccount,
+        quoteTokenAccount: otherUserQuoteTokenAddress,
+        owner: otherUser,
+        amount: otherUserPutOptionAmount,
+      },
+    ];
+    let remainingAccounts = [];
+    for (let holder of holders) {
+      remainingAccounts.push({
+        pubkey: holder.optionTokenAccount,
+        isWritable: true,
+        isSigner: false,
+      });
+      remainingAccounts.push({
+        pubkey: holder.quoteTokenAccount,
+        isWritable: true,
+        isSigner: false,
+      });
+    }
+
+    let accounts = {
+      state,
+      underlying,
+      vault: putVault,
+      underlyingMint: token.publicKey,
+      collateralMint: quoteToken.publicKey,
+      treasury: quoteTreasury,
+      optionAccount: putOptionAccount,
+      optionMint: putOptionMint,
+      mintAuthority,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      vaultAuthority,
+    };
+
+    let putOptionToken = new Token(
+      provider.connection,
+      putOptionMint,
+      TOKEN_PROGRAM_ID,
+      (provider.wallet as anchor.Wallet).payer
+    );
+    let getQuoteBalances = async () => {
+      let balances = [];
+      for (let holder of holders) {
+        balances.push(
+          (
+            await utils.getTokenAccountInfo(
+              provider.connection,
+              holder.quoteTokenAccount
+            )
+          ).amount.toNumber()
+        );
+      }
+      return balances;
+    };
+    let prevQuoteBalances = await getQuoteBalances();
+
+    // Holders that haven't delegated are skipped rather than failing the
+    // batch.
+    for (let i = 0; i < holders.length; i++) {
+      await putOptionToken.approve(
+        holders[i].optionTokenAccount,
+        mintAuthority,
+        holders[i].owner,
+        [],
+        holders[i].amount
+      );
+      await program.rpc.autoExercise({ accounts, remainingAccounts });
+
+      let quoteBalances = await getQuoteBalances();
+      for (let j = 0; j < holders.length; j++) {
+        let payout = j <= i ? putProfitPerOption * holders[j].amount : 0;
+        assert.ok(quoteBalances[j] - prevQuoteBalances[j] == payout);
+      }
+    }
+
+    let mintInfo = await utils.getMintInfo(provider.connection, putOptionMint);
+    assert.ok(mintInfo.supply.toNumber() == 0);
+
+    let vaultInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      putVault
+    );
+    assert.ok(vaultInfo.amount.toNumber() == 0);
+  });
+
+  it("Settle option on the median of recorded observations.", async () => {
+    let observed = await getOptionAddresses(
+      program.programId,
+      underlying,
+      5,
+      provider.wallet.publicKey
+    );
+    let observedOptionAmount = 10 ** OPTION_MINT_DECIMALS;
+    let observedCollateralAmount = observedOptionAmount * minLotSize;
+    let observedExpirationTs = Date.now() / 1000 + 10;
+
+    await token.mintTo(
+      userTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      observedCollateralAmount
+    );
+
+    let args = {
+      collateralAmount: new anchor.BN(observedCollateralAmount),
+      optionAccountNonce: observed.optionAccountNonce,
+      optionMintNonce: observed.optionMintNonce,
+      tokenAccountNonce: observed.tokenAccountNonce,
+      vaultNonce: observed.vaultNonce,
+      settlementVaultNonce: observed.settlementVaultNonce,
+      writerMintNonce: observed.writerMintNonce,
+      writerTokenAccountNonce: observed.writerTokenAccountNonce,
+      expiry: new anchor.BN(observedExpirationTs),
+      strike,
+      shortStrike: new anchor.BN(0),
+      kind: { call: {} },
+      settlementMode: { cash: {} },
+      exerciseStyle: { european: {} },
+      settlementSource: { observationMedian: {} },
+    };
+
+    await program.rpc.initializeOption(args, {
+      accounts: {
+        state,
+        underlying,
+        oracle,
+        vault: observed.vault,
+        vaultAuthority,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        treasury,
+        settlementMint: quoteToken.publicKey,
+        settlementVault: observed.settlementVault,
+        collateralTokenAccount: userTokenAddress,
+        creator: provider.wallet.publicKey,
+        optionAccount: observed.optionAccount,
+        mintAuthority,
+        optionMint: observed.optionMint,
+        userOptionTokenAccount: observed.userOptionTokenAccount,
+        writerMint: observed.writerMint,
+        userWriterTokenAccount: observed.userWriterTokenAccount,
+        optionMetadata: observed.optionMetadata,
+        metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      },
+    });
+
+    let [settlementObservations, settlementObservationsNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(
+            anchor.utils.bytes.utf8.encode("settlement-observations")
+          ),
+          observed.optionAccount.toBuffer(),
+        ],
+        program.programId
+      );
+
+    await program.rpc.initializeSettlementObservations(
+      settlementObservationsNonce,
+      {
+        accounts: {
+          settlementObservations,
+          optionAccount: observed.optionAccount,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+      }
+    );
+
+    // Observation based options can't settle on a single print.
+    await utils.expectError(async () => {
+      await program.rpc.expireOption({
+        accounts: {
+          state,
+          underlying,
+          underlyingMint: token.publicKey,
+          collateralMint: token.publicKey,
+          optionAccount: observed.optionAccount,
+          oracle,
+          optionMint: observed.optionMint,
+          vault: observed.vault,
+        },
+      });
+    }, "Settlement source requires observations");
+
+    let observedPrices = [170, 190, oraclePrice];
+    for (let price of observedPrices) {
+      await setFeedPrice(pythProgram, price, oracle);
+      await program.rpc.recordSettlementObservation({
+        accounts: {
+          state,
+          underlying,
+          underlyingMint: token.publicKey,
+          optionAccount: observed.optionAccount,
+          settlementObservations,
+          oracle,
+        },
+      });
+    }
+
+    let observationsInfo = await program.account.settlementObservations.fetch(
+      settlementObservations
+    );
+    assert.ok(observationsInfo.prices.length == observedPrices.length);
+    let observedAccountInfo = await program.account.optionAccount.fetch(
+      observed.optionAccount
+    );
+    assert.deepEqual(observedAccountInfo.status, { settling: {} });
+
+    let expireAccounts = {
+      state,
+      underlying,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      optionAccount: observed.optionAccount,
+      settlementObservations,
+      optionMint: observed.optionMint,
+      vault: observed.vault,
+    };
+
+    // Keepers can still sample after expiry until the window closes.
+    await utils.sleepTillTime(observedExpirationTs);
+    await utils.expectError(async () => {
+      await program.rpc.expireOptionFromObservations({
+        accounts: expireAccounts,
+      });
+    }, "Settlement observation window still open");
+
+    await utils.sleepTillTime(
+      observedExpirationTs + settlementPriceThresholdSeconds
+    );
+    await program.rpc.expireOptionFromObservations({
+      accounts: expireAccounts,
+    });
+
+    let medianPrice = 175 * Math.pow(10, 6);
+    let medianProfitPerOption = Math.floor(
+      (minLotSize * (medianPrice - strike.toNumber())) / medianPrice
+    );
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      observed.optionAccount
+    );
+    assert.ok(optionAccountInfo.settlementPrice.toNumber() == medianPrice);
+    assert.ok(
+      optionAccountInfo.profitPerOption.toNumber() == medianProfitPerOption
+    );
+  });
+
+  it("Spot options can't record settlement observations.", async () => {
+    let [settlementObservations, settlementObservationsNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(
+            anchor.utils.bytes.utf8.encode("settlement-observations")
+          ),
+          optionAccount.toBuffer(),
+        ],
+        program.programId
+      );
+
+    await utils.expectError(async () => {
+      await program.rpc.initializeSettlementObservations(
+        settlementObservationsNonce,
+        {
+          accounts: {
+            settlementObservations,
+            optionAccount,
+            payer: provider.wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+        }
+      );
+    }, "Settlement source does not use observations");
+
+    // Without an observations account there is nothing to record into.
+    await assert.rejects(
+      program.rpc.recordSettlementObservation({
+        accounts: {
+          state,
+          underlying,
+          underlyingMint: token.publicKey,
+          optionAccount,
+          settlementObservations,
+          oracle,
+        },
+      })
+    );
+  });
+
+  it("Admin collects mint fees from the treasury.", async () => {
+    let mintFeeBps = 10;
+    await utils.expectError(async () => {
+      await program.rpc.setFees(
+        new anchor.BN(10_001),
+        new anchor.BN(0),
+        new anchor.BN(0),
+        {
+          accounts: { state, admin: admin.publicKey },
+          signers: [admin],
+        }
+      );
+    }, "Invalid fee rate");
+
+    await program.rpc.setFees(
+      new anchor.BN(mintFeeBps),
+      new anchor.BN(0),
+      new anchor.BN(0),
+      {
+        accounts: { state, admin: admin.publicKey },
+        signers: [admin],
+      }
+    );
+
+    let feeOption = await getOptionAddresses(
+      program.programId,
+      underlying,
+      6,
+      provider.wallet.publicKey
+    );
+    let feeCollateralAmount = 10 ** OPTION_MINT_DECIMALS * minLotSize;
+    let mintFee = (feeCollateralAmount * mintFeeBps) / 10_000;
+
+    // Writers have to cover the fee on top of the collateral.
+    let shortTokenAddress = await token.createAccount(
+      provider.wallet.publicKey
+    );
+    await token.mintTo(
+      shortTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      feeCollateralAmount
+    );
+    await token.mintTo(
+      userTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      feeCollateralAmount + mintFee
+    );
+
+    let args = {
+      collateralAmount: new anchor.BN(feeCollateralAmount),
+      optionAccountNonce: feeOption.optionAccountNonce,
+      optionMintNonce: feeOption.optionMintNonce,
+      tokenAccountNonce: feeOption.tokenAccountNonce,
+      vaultNonce: feeOption.vaultNonce,
+      settlementVaultNonce: feeOption.settlementVaultNonce,
+      writerMintNonce: feeOption.writerMintNonce,
+      writerTokenAccountNonce: feeOption.writerTokenAccountNonce,
+      expiry: new anchor.BN(Date.now() / 1000 + 1000),
+      strike,
+      shortStrike: new anchor.BN(0),
+      kind: { call: {} },
+      settlementMode: { cash: {} },
+      exerciseStyle: { european: {} },
+      settlementSource: { spot: {} },
+    };
+
+    let initializeAccounts = {
+      state,
+      underlying,
+      oracle,
+      vault: feeOption.vault,
+      vaultAuthority,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      treasury,
+      settlementMint: quoteToken.publicKey,
+      settlementVault: feeOption.settlementVault,
+      collateralTokenAccount: userTokenAddress,
+      creator: provider.wallet.publicKey,
+      optionAccount: feeOption.optionAccount,
+      mintAuthority,
+      optionMint: feeOption.optionMint,
+      userOptionTokenAccount: feeOption.userOptionTokenAccount,
+      writerMint: feeOption.writerMint,
+      userWriterTokenAccount: feeOption.userWriterTokenAccount,
+      optionMetadata: feeOption.optionMetadata,
+      metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+    };
+    await utils.expectError(async () => {
+      await program.rpc.initializeOption(args, {
+        accounts: {
+          ...initializeAccounts,
+          collateralTokenAccount: shortTokenAddress,
+        },
+      });
+    }, "Insufficient funds");
+
+    await program.rpc.initializeOption(args, { accounts: initializeAccounts });
+
+    let vaultInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      feeOption.vault
+    );
+    let treasuryInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      treasury
+    );
+    assert.ok(vaultInfo.amount.toNumber() == feeCollateralAmount);
+    assert.ok(treasuryInfo.amount.toNumber() == mintFee);
+
+    await program.rpc.withdrawFromTreasury(new anchor.BN(mintFee), {
+      accounts: {
+        state,
+        treasury,
+        destination: userTokenAddress,
+        admin: admin.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      },
+      signers: [admin],
+    });
+
+    treasuryInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      treasury
+    );
+    assert.ok(treasuryInfo.amount.toNumber() == 0);
+
+    await program.rpc.setFees(
+      new anchor.BN(0),
+      new anchor.BN(0),
+      new anchor.BN(0),
+      {
+        accounts: { state, admin: admin.publicKey },
+        signers: [admin],
+      }
+    );
+  });
+
+  it("Listing authority restricts expiries to a daily calendar.", async () => {
+    // Daily at 08:00 UTC.
+    let timeOfDaySeconds = 8 * 60 * 60;
+    let calendar = {
+      timeOfDaySeconds,
+      daily: true,
+      weekly: false,
+      monthly: false,
+    };
+    let calendarAccounts = {
+      state,
+      underlying,
+      listingAuthority: admin.publicKey,
+    };
+
+    await utils.expectError(async () => {
+      await program.rpc.setExpiryCalendar(
+        { ...calendar, timeOfDaySeconds: 24 * 60 * 60 },
+        { accounts: calendarAccounts, signers: [admin] }
+      );
+    }, "Invalid expiry calendar");
+
+    await program.rpc.setExpiryCalendar(calendar, {
+      accounts: calendarAccounts,
+      signers: [admin],
+    });
+
+    let listed = await getOptionAddresses(
+      program.programId,
+      underlying,
+      7,
+      provider.wallet.publicKey
+    );
+    let listedCollateralAmount = 10 ** OPTION_MINT_DECIMALS * minLotSize;
+    await token.mintTo(
+      userTokenAddress,
+      tokenMintAuthority.publicKey,
+      [tokenMintAuthority],
+      listedCollateralAmount
+    );
+
+    let now = Math.floor(Date.now() / 1000);
+    let secondsPerDay = 24 * 60 * 60;
+    let nextExpiry =
+      Math.ceil((now - timeOfDaySeconds) / secondsPerDay) * secondsPerDay +
+      timeOfDaySeconds;
+    let args = {
+      collateralAmount: new anchor.BN(listedCollateralAmount),
+      optionAccountNonce: listed.optionAccountNonce,
+      optionMintNonce: listed.optionMintNonce,
+      tokenAccountNonce: listed.tokenAccountNonce,
+      vaultNonce: listed.vaultNonce,
+      settlementVaultNonce: listed.settlementVaultNonce,
+      writerMintNonce: listed.writerMintNonce,
+      writerTokenAccountNonce: listed.writerTokenAccountNonce,
+      expiry: new anchor.BN(now + 60 * 60),
+      strike,
+      shortStrike: new anchor.BN(0),
+      kind: { call: {} },
+      settlementMode: { cash: {} },
+      exerciseStyle: { european: {} },
+      settlementSource: { spot: {} },
+    };
+    let accounts = {
+      state,
+      underlying,
+      oracle,
+      vault: listed.vault,
+      vaultAuthority,
+      underlyingMint: token.publicKey,
+      collateralMint: token.publicKey,
+      treasury,
+      settlementMint: quoteToken.publicKey,
+      settlementVault: listed.settlementVault,
+      collateralTokenAccount: userTokenAddress,
+      creator: provider.wallet.publicKey,
+      optionAccount: listed.optionAccount,
+      mintAuthority,
+      optionMint: listed.optionMint,
+      userOptionTokenAccount: listed.userOptionTokenAccount,
+      writerMint: listed.writerMint,
+      userWriterTokenAccount: listed.userWriterTokenAccount,
+      optionMetadata: listed.optionMetadata,
+      metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+    };
+
+    await utils.expectError(async () => {
+      await program.rpc.initializeOption(args, { accounts });
+    }, "Expiry not on calendar");
+
+    // Clients read the upcoming expiries by simulating the instruction.
+    let simulation = await program.simulate.getNextValidExpiries(2, {
+      accounts: { underlying },
+    });
+    let validExpiries = simulation.events.find(
+      (e) => e.name == "ValidExpiries"
+    ).data.expiries;
+    assert.deepEqual(
+      validExpiries.map((e) => e.toNumber()),
+      [nextExpiry, nextExpiry + secondsPerDay]
+    );
+
+    args.expiry = new anchor.BN(nextExpiry);
+    await program.rpc.initializeOption(args, { accounts });
+
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      listed.optionAccount
+    );
+    assert.ok(optionAccountInfo.expiry.toNumber() == nextExpiry);
+
+    // Back to unrestricted expiries.
+    await program.rpc.setExpiryCalendar(
+      { ...calendar, daily: false },
+      { accounts: calendarAccounts, signers: [admin] }
+    );
+  });
+
+  it("Guardian pauses minting and exercise.", async () => {
+    let unpaused = {
+      mint: false,
+      burn: false,
+      settlement: false,
+      exercise: false,
+    };
+    await program.rpc.setPauseFlags(
+      { ...unpaused, mint: true },
+      {
+        accounts: { state, guardian: guardian.publicKey },
+        signers: [guardian],
+      }
+    );
+
+    let writeMoreOptions = async () => {
+      await program.rpc.writeMoreOptions(
+        new anchor.BN(otherUserPutCollateralAmount),
+        {
+          accounts: {
+            state,
+            underlying,
+            vault: putVault,
+            underlyingMint: token.publicKey,
+            collateralMint: quoteToken.publicKey,
+            treasury: quoteTreasury,
+            collateralTokenAccount: otherUserQuoteTokenAddress,
+            writer: otherUser.publicKey,
+            optionAccount: putOptionAccount,
+            mintAuthority,
+            optionMint: putOptionMint,
+            userOptionTokenAccount: otherUserPutOptionAccount,
+            writerMint: putWriterMint,
+            userWriterTokenAccount: otherUserPutWriterTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+          signers: [otherUser],
+        }
+      );
+    };
+    await utils.expectError(writeMoreOptions, "Minting paused");
+
+    // Unpaused, the write reaches the expiry check instead.
+    await program.rpc.setPauseFlags(unpaused, {
+      accounts: { state, guardian: guardian.publicKey },
+      signers: [guardian],
+    });
+    await utils.expectError(
+      writeMoreOptions,
+      "Cannot write options after expiry"
+    );
+
+    await utils.expectError(async () => {
+      await program.rpc.setUnderlyingPauseFlags(
+        { ...unpaused, exercise: true },
+        {
+          accounts: { state, underlying, guardian: admin.publicKey },
+          signers: [admin],
+        }
+      );
+    }, "Unauthorized guardian");
+
+    await program.rpc.setUnderlyingPauseFlags(
+      { ...unpaused, exercise: true },
+      {
+        accounts: { state, underlying, guardian: guardian.publicKey },
+        signers: [guardian],
+      }
+    );
+
+    await utils.expectError(async () => {
+      await program.rpc.exerciseOption({
+        accounts: {
+          state,
+          underlying,
+          vault,
+          underlyingMint: token.publicKey,
+          collateralMint: token.publicKey,
+          treasury,
+          collateralTokenAccount: userTokenAddress,
+          oracle,
+          settlementMint: quoteToken.publicKey,
+          settlementVault,
+          settlementTokenAccount: userQuoteTokenAddress,
+          authority: provider.wallet.publicKey,
+          optionAccount,
+          optionMint,
+          userOptionTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          vaultAuthority,
+        },
+      });
+    }, "Exercise paused");
+
+    await program.rpc.setUnderlyingPauseFlags(unpaused, {
+      accounts: { state, underlying, guardian: guardian.publicKey },
+      signers: [guardian],
+    });
+    let underlyingAccount = await program.account.underlying.fetch(underlying);
+    assert.deepEqual(underlyingAccount.pauseFlags, unpaused);
+  });
+
+  it("Creator closes the fully claimed option.", async () => {
+    let creatorBalance = await provider.connection.getBalance(
+      provider.wallet.publicKey
+    );
+
+    let closedEvent = await captureEvent(program, "OptionClosed", () =>
+      program.rpc.closeOption({
+        accounts: {
+          state,
+          underlying,
+          underlyingMint: token.publicKey,
+          optionAccount,
+          vault,
+          settlementVault,
+          optionMint,
+          writerMint,
+          treasury,
+          settlementTreasury: quoteTreasury,
+          creator: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          vaultAuthority,
+        },
+      })
+    );
+    assert.ok(closedEvent.optionAccount.equals(optionAccount));
+    // Everything was claimed, so nothing is left to sweep.
+    assert.ok(closedEvent.sweptCollateral.toNumber() == 0);
+
+    assert.ok(
+      (await provider.connection.getAccountInfo(optionAccount)) == null
+    );
+    assert.ok((await provider.connection.getAccountInfo(vault)) == null);
+    assert.ok(
+      (await provider.connection.getBalance(provider.wallet.publicKey)) >
+        creatorBalance
+    );
+  });
+
+  it("Creator closes a fully burned series before expiry.", async () => {
+    let feeOption = await getOptionAddresses(
+      program.programId,
+      underlying,
+      6,
+      provider.wallet.publicKey
+    );
+    await program.rpc.burnOption(new anchor.BN(10 ** OPTION_MINT_DECIMALS), {
+      accounts: {
+        state,
+        underlying,
+        vault: feeOption.vault,
+        underlyingMint: token.publicKey,
+        collateralMint: token.publicKey,
+        collateralTokenAccount: userTokenAddress,
+        authority: provider.wallet.publicKey,
+        optionAccount: feeOption.optionAccount,
+        mintAuthority,
+        optionMint: feeOption.optionMint,
+        userOptionTokenAccount: feeOption.userOptionTokenAccount,
+        writerMint: feeOption.writerMint,
+        userWriterTokenAccount: feeOption.userWriterTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+    });
+
+    // No settlement is needed once nothing is outstanding.
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      feeOption.optionAccount
+    );
+    assert.deepEqual(optionAccountInfo.status, { active: {} });
+    await program.rpc.closeOption({
+      accounts: {
+        state,
+        underlying,
+        underlyingMint: token.publicKey,
+        optionAccount: feeOption.optionAccount,
+        vault: feeOption.vault,
+        settlementVault: feeOption.settlementVault,
+        optionMint: feeOption.optionMint,
+        writerMint: feeOption.writerMint,
+        treasury,
+        settlementTreasury: quoteTreasury,
+        creator: provider.wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        vaultAuthority,
+      },
+    });
+    assert.ok(
+      (await provider.connection.getAccountInfo(feeOption.optionAccount)) ==
+        null
+    );
+  });
+
+  it("Unclaimed collateral is swept after the claim period.", async () => {
+    let observed = await getOptionAddresses(
+      program.programId,
+      underlying,
+      5,
+      provider.wallet.publicKey
+    );
+    let accounts = {
+      state,
+      underlying,
+      underlyingMint: token.publicKey,
+      optionAccount: observed.optionAccount,
+      vault: observed.vault,
+      settlementVault: observed.settlementVault,
+      optionMint: observed.optionMint,
+      writerMint: observed.writerMint,
+      treasury,
+      settlementTreasury: quoteTreasury,
+      creator: provider.wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      vaultAuthority,
+    };
+
+    // Nobody has exercised or collected from this series yet.
+    let optionAccountInfo = await program.account.optionAccount.fetch(
+      observed.optionAccount
+    );
+    let claimDeadline =
+      Math.max(
+        optionAccountInfo.expiry.toNumber(),
+        optionAccountInfo.settlementTs.toNumber() + settlementDisputeSeconds
+      ) + claimPeriodSeconds;
+    await utils.expectError(async () => {
+      await program.rpc.closeOption({ accounts });
+    }, "Claim period active");
+    await utils.sleepTillTime(claimDeadline);
+
+    // Sweeping moves collateral, so the guardian can halt it.
+    let unpaused = {
+      mint: false,
+      burn: false,
+      settlement: false,
+      exercise: false,
+    };
+    let pauseAccounts = { state, guardian: guardian.publicKey };
+    await program.rpc.setPauseFlags(
+      { ...unpaused, burn: true },
+      { accounts: pauseAccounts, signers: [guardian] }
+    );
+    await utils.expectError(async () => {
+      await program.rpc.closeOption({ accounts });
+    }, "Burning paused");
+    await program.rpc.setPauseFlags(unpaused, {
+      accounts: pauseAccounts,
+      signers: [guardian],
+    });
+
+    let vaultInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      observed.vault
+    );
+    let treasuryInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      treasury
+    );
+    let prevTreasuryBalance = treasuryInfo.amount.toNumber();
+
+    // The observations account is closed along with the series.
+    let [settlementObservations] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [
+          Buffer.from(
+            anchor.utils.bytes.utf8.encode("settlement-observations")
+          ),
+          observed.optionAccount.toBuffer(),
+        ],
+        program.programId
+      );
+    await utils.expectError(async () => {
+      await program.rpc.closeOption({ accounts });
+    }, "Invalid settlement observations account");
+    await program.rpc.closeOption({
+      accounts,
+      remainingAccounts: [
+        { pubkey: settlementObservations, isWritable: true, isSigner: false },
+      ],
+    });
+
+    treasuryInfo = await utils.getTokenAccountInfo(
+      provider.connection,
+      treasury
+    );
+    assert.ok(
+      treasuryInfo.amount.toNumber() ==
+        prevTreasuryBalance + vaultInfo.amount.toNumber()
+    );
+    assert.ok(
+      (await provider.connection.getAccountInfo(observed.optionAccount)) ==
+        null
+    );
+    assert.ok(
+      (await provider.connection.getAccountInfo(settlementObservations)) ==
+        null
+    );
+  });
+
+  it("Admin swaps the underlying oracle after the timelock.", async () => {
+    let newOracle = await createPriceFeed({
+      oracleProgram: pythProgram,
+      initPrice: oraclePrice,
+      confidence: oracleConfidence,
+      keypair: Keypair.generate(),
+      expo: -8,
+    });
+
+    let [queuedAction, queuedActionNonce] = await getQueuedActionAddress();
+    await program.rpc.queueAdminAction(
+      queuedActionNonce,
+      { setUnderlyingOracle: { underlying, oracle: newOracle } },
+      {
+        accounts: {
+          state,
+          queuedAction,
+          admin: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [admin],
+      }
+    );
+
+    let executeArgs = {
+      accounts: { state, queuedAction, admin: admin.publicKey },
+      remainingAccounts: [
+        { pubkey: underlying, isWritable: true, isSigner: false },
+      ],
+      signers: [admin],
+    };
+    await utils.expectError(async () => {
+      await program.rpc.executeAdminAction(executeArgs);
+    }, "Admin action not ready");
+
+    let queuedActionInfo = await program.account.queuedAction.fetch(
+      queuedAction
+    );
+    await utils.sleepTillTime(queuedActionInfo.eta.toNumber());
+    await program.rpc.executeAdminAction(executeArgs);
+
+    let underlyingAccount = await program.account.underlying.fetch(underlying);
+    assert.ok(underlyingAccount.oracle.equals(newOracle));
+    assert.ok(underlyingAccount.oracles[0].equals(newOracle));
+    assert.ok((await provider.connection.getAccountInfo(queuedAction)) == null);
+  });
+
+  it("Admin loosens the oracle limits after the timelock.", async () => {
+    let queuedAction = await queueAdminAction({
+      setOracleLimits: {
+        underlying,
+        maxConfidenceBps: new anchor.BN(2 * maxConfidenceBps),
+        maxStalenessSlots: new anchor.BN(2 * maxStalenessSlots),
+      },
+    });
+    await utils.expectError(async () => {
+      await executeUnderlyingAdminAction(queuedAction);
+    }, "Admin action not ready");
+
+    let queuedActionInfo = await program.account.queuedAction.fetch(
+      queuedAction
+    );
+    await utils.sleepTillTime(queuedActionInfo.eta.toNumber());
+    await executeUnderlyingAdminAction(queuedAction);
+
+    let underlyingAccount = await program.account.underlying.fetch(underlying);
+    assert.ok(
+      underlyingAccount.maxConfidenceBps.toNumber() == 2 * maxConfidenceBps
+    );
+    assert.ok(
+      underlyingAccount.maxStalenessSlots.toNumber() == 2 * maxStalenessSlots
+    );
+  });
+
+  it("Admin cancels a queued settlement threshold change.", async () => {
+    let [queuedAction, queuedActionNonce] = await getQueuedActionAddress();
+    await program.rpc.queueAdminAction(
+      queuedActionNonce,
+      { setSettlementPriceThresholdSeconds: { seconds: 60 } },
+      {
+        accounts: {
+          state,
+          queuedAction,
+          admin: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [admin],
+      }
+    );
+
+    await program.rpc.cancelAdminAction({
+      accounts: { state, queuedAction, admin: admin.publicKey },
+      signers: [admin],
+    });
+
+    assert.ok((await provider.connection.getAccountInfo(queuedAction)) == null);
+    let stateAccount = await program.account.state.fetch(state);
+    assert.ok(
+      stateAccount.settlementPriceThresholdSeconds ==
+        settlementPriceThresholdSeconds
+    );
+  });
+
+  it("Admin changes the dispute window after the timelock.", async () => {
+    let queuedAction = await queueAdminAction({
+      setSettlementDisputeSeconds: { seconds: 2 * settlementDisputeSeconds },
+    });
+    let executeArgs = {
+      accounts: { state, queuedAction, admin: admin.publicKey },
+      signers: [admin],
+    };
+    await utils.expectError(async () => {
+      await program.rpc.executeAdminAction(executeArgs);
+    }, "Admin action not ready");
+
+    let queuedActionInfo = await program.account.queuedAction.fetch(
+      queuedAction
+    );
+    await utils.sleepTillTime(queuedActionInfo.eta.toNumber());
+    await program.rpc.executeAdminAction(executeArgs);
+
+    let stateAccount = await program.account.state.fetch(state);
+    assert.ok(
+      stateAccount.settlementDisputeSeconds == 2 * settlementDisputeSeconds
+    );
+  });
+
+  it("Admin hands over to a new admin and splits roles.", async () => {
+    const newAdmin = Keypair.generate();
+    await program.rpc.proposeAdmin({
+      accounts: { state, newAdmin: newAdmin.publicKey, admin: admin.publicKey },
+      signers: [admin],
+    });
+
+    await utils.expectError(async () => {
+      await program.rpc.acceptAdmin({
+        accounts: { state, newAdmin: otherUser.publicKey },
+        signers: [otherUser],
+      });
+    }, "Unauthorized pending admin");
+
+    await program.rpc.acceptAdmin({
+      accounts: { state, newAdmin: newAdmin.publicKey },
+      signers: [newAdmin],
+    });
+
+    let stateAccount = await program.account.state.fetch(state);
+    assert.ok(stateAccount.admin.equals(newAdmin.publicKey));
+    assert.ok(stateAccount.pendingAdmin.equals(new PublicKey(0)));
+
+    let rolesAccounts = {
+      state,
+      listingAuthority: otherUser.publicKey,
+      settlementAuthority: otherUser.publicKey,
+      guardian: guardian.publicKey,
+      admin: admin.publicKey,
+    };
+    await utils.expectError(async () => {
+      await program.rpc.updateRoles({
+        accounts: rolesAccounts,
+        signers: [admin],
+      });
+    }, "Unauthorized admin");
+
+    await program.rpc.updateRoles({
+      accounts: { ...rolesAccounts, admin: newAdmin.publicKey },
+      signers: [newAdmin],
+    });
+
+    stateAccount = await program.account.state.fetch(state);
+    assert.ok(stateAccount.listingAuthority.equals(otherUser.publicKey));
+    assert.ok(stateAccount.settlementAuthority.equals(otherUser.publicKey));
+    assert.ok(stateAccount.guardian.equals(guardian.publicKey));
+  });
 });